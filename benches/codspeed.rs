@@ -49,4 +49,17 @@ benchmark_days!(
     1, 2, 3, 4, 5, 6, /*7,*/ 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
     25
 );
-criterion_main!(days);
+
+fn day22_lanes(c: &mut Criterion) {
+    let input = load_input(22);
+    use aoc24::day22::lanes::{part1, part2};
+    c.bench_function("day 22 part 1 lanes", |b| {
+        b.iter(|| part1(black_box(&input)))
+    });
+    c.bench_function("day 22 part 2 lanes", |b| {
+        b.iter(|| part2(black_box(&input)))
+    });
+}
+
+criterion_group!(variants, day22_lanes);
+criterion_main!(days, variants);
@@ -1,5 +1,10 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use crate::util::{ComponentLabeller, Connectivity, Grid};
+
+pub mod regions;
+pub mod streaming;
+
 type Output = usize;
 type Plant = u8;
 
@@ -56,6 +61,20 @@ impl Grid2D {
     }
 }
 
+impl Grid<Plant> for Grid2D {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> Plant {
+        Grid2D::get(self, x, y)
+    }
+}
+
 type Input = Grid2D;
 
 #[aoc_generator(day12)]
@@ -473,6 +492,28 @@ fn two(plots: &Input) -> Output {
         .sum()
 }
 
+#[aoc(day12, part1, labeller)]
+fn one_labeller(plots: &Input) -> Output {
+    ComponentLabeller::new(Connectivity::Four)
+        .with_perimeter()
+        .label(plots, |a, b| a == b)
+        .stats
+        .iter()
+        .map(|stats| stats.area * stats.perimeter.unwrap())
+        .sum()
+}
+
+#[aoc(day12, part2, labeller)]
+fn two_labeller(plots: &Input) -> Output {
+    ComponentLabeller::new(Connectivity::Four)
+        .with_corners()
+        .label(plots, |a, b| a == b)
+        .stats
+        .iter()
+        .map(|stats| stats.area * stats.corners.unwrap())
+        .sum()
+}
+
 pub fn part1(puzzle: &str) -> Output {
     one(&parse(puzzle))
 }
@@ -532,4 +573,13 @@ mod examples {
         let res = two(&parse(include_str!("test.txt")));
         assert_eq!(res, 1206);
     }
+
+    #[test]
+    fn example_labeller() {
+        let plots = parse(include_str!("test.txt"));
+        assert_eq!(one_labeller(&plots), 1930);
+        assert_eq!(two_labeller(&plots), 1206);
+        let plots = parse(include_str!("test_abba.txt"));
+        assert_eq!(two_labeller(&plots), 368);
+    }
 }
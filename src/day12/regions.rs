@@ -0,0 +1,230 @@
+use super::{parse, Plant, RegionId};
+use crate::util::{ComponentLabeller, Connectivity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub min_x: usize,
+    pub min_y: usize,
+    pub max_x: usize,
+    pub max_y: usize,
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> usize {
+        self.max_x - self.min_x + 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.max_y - self.min_y + 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionReport {
+    pub id: RegionId,
+    pub plant: Plant,
+    pub area: usize,
+    pub perimeter: usize,
+    pub sides: usize,
+    pub bounds: BoundingBox,
+    /// The region's plots in row-major order.
+    pub cells: Vec<(usize, usize)>,
+    /// Number of separate patches of other plants the region fully encloses.
+    pub holes: usize,
+    /// Regions lying inside those holes, including regions nested in them.
+    pub enclosed: Vec<RegionId>,
+}
+
+impl RegionReport {
+    pub fn price(&self) -> usize {
+        self.area * self.perimeter
+    }
+
+    pub fn discounted_price(&self) -> usize {
+        self.area * self.sides
+    }
+
+    /// Row-major membership mask over the bounding box.
+    pub fn mask(&self) -> Vec<bool> {
+        let mut mask = vec![false; self.bounds.width() * self.bounds.height()];
+        for &(x, y) in &self.cells {
+            mask[(y - self.bounds.min_y) * self.bounds.width() + x - self.bounds.min_x] = true;
+        }
+        mask
+    }
+}
+
+/// Every region of a garden together with a label for each plot.
+#[derive(Debug, Clone)]
+pub struct RegionMap {
+    pub width: usize,
+    pub height: usize,
+    labels: Vec<RegionId>,
+    pub regions: Vec<RegionReport>,
+}
+
+impl RegionMap {
+    pub fn region_at(&self, x: usize, y: usize) -> &RegionReport {
+        &self.regions[self.labels[y * self.width + x]]
+    }
+
+    pub fn labels(&self) -> &[RegionId] {
+        &self.labels
+    }
+
+    fn same(&self, id: RegionId, x: isize, y: isize) -> bool {
+        (0..self.width as isize).contains(&x)
+            && (0..self.height as isize).contains(&y)
+            && self.labels[y as usize * self.width + x as usize] == id
+    }
+
+    /// Finds the holes of region `id` and the regions inside them.
+    fn find_holes(&mut self, id: RegionId) {
+        let region = &self.regions[id];
+        // Flood the complement from a ring around the bounding box, whatever stays dry is a hole.
+        let bounds = region.bounds;
+        let (min_x, min_y) = (bounds.min_x as isize - 1, bounds.min_y as isize - 1);
+        let (width, height) = (bounds.width() + 2, bounds.height() + 2);
+        let mut reached = vec![false; width * height];
+        let index = |x: isize, y: isize| (y - min_y) as usize * width + (x - min_x) as usize;
+        let inside = |x: isize, y: isize| {
+            (min_x..min_x + width as isize).contains(&x)
+                && (min_y..min_y + height as isize).contains(&y)
+        };
+        let flood = |reached: &mut Vec<bool>, start: (isize, isize)| {
+            let mut stack = vec![start];
+            reached[index(start.0, start.1)] = true;
+            let mut cells = Vec::new();
+            while let Some((x, y)) = stack.pop() {
+                cells.push((x, y));
+                for (x, y) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if inside(x, y) && !reached[index(x, y)] && !self.same(id, x, y) {
+                        reached[index(x, y)] = true;
+                        stack.push((x, y));
+                    }
+                }
+            }
+            cells
+        };
+        flood(&mut reached, (min_x, min_y));
+
+        let mut holes = 0;
+        let mut enclosed = Vec::new();
+        for y in bounds.min_y..=bounds.max_y {
+            for x in bounds.min_x..=bounds.max_x {
+                let (x, y) = (x as isize, y as isize);
+                if reached[index(x, y)] || self.same(id, x, y) {
+                    continue;
+                }
+                holes += 1;
+                enclosed.extend(
+                    flood(&mut reached, (x, y))
+                        .into_iter()
+                        .map(|(x, y)| self.labels[y as usize * self.width + x as usize]),
+                );
+            }
+        }
+        enclosed.sort_unstable();
+        enclosed.dedup();
+
+        let region = &mut self.regions[id];
+        region.holes = holes;
+        region.enclosed = enclosed;
+    }
+}
+
+/// Labels every region of the garden and measures it, numbering regions by their first plot
+/// in row-major order.
+pub fn regions(puzzle: &str) -> RegionMap {
+    let plots = parse(puzzle);
+    let components = ComponentLabeller::new(Connectivity::Four)
+        .with_perimeter()
+        .with_corners()
+        .label(&plots, |a, b| a == b);
+    let mut cells = vec![Vec::new(); components.stats.len()];
+    for y in 0..plots.height {
+        for x in 0..plots.width {
+            cells[components.label(x, y)].push((x, y));
+        }
+    }
+    let regions = cells
+        .into_iter()
+        .zip(&components.stats)
+        .enumerate()
+        .map(|(id, (cells, stats))| RegionReport {
+            id,
+            plant: plots.get(cells[0].0, cells[0].1),
+            area: stats.area,
+            perimeter: stats.perimeter.unwrap(),
+            sides: stats.corners.unwrap(),
+            bounds: BoundingBox {
+                min_x: cells.iter().map(|c| c.0).min().unwrap(),
+                min_y: cells[0].1,
+                max_x: cells.iter().map(|c| c.0).max().unwrap(),
+                max_y: cells[cells.len() - 1].1,
+            },
+            cells,
+            holes: 0,
+            enclosed: Vec::new(),
+        })
+        .collect();
+    let mut map = RegionMap {
+        width: plots.width,
+        height: plots.height,
+        labels: components.labels,
+        regions,
+    };
+    for id in 0..map.regions.len() {
+        map.find_holes(id);
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day12::{one, two};
+
+    #[test]
+    fn matches_prices() {
+        for input in [
+            include_str!("test.txt"),
+            include_str!("test_small.txt"),
+            include_str!("test_medium.txt"),
+            include_str!("test_e.txt"),
+            include_str!("test_abba.txt"),
+        ] {
+            let map = regions(input);
+            let price = map.regions.iter().map(RegionReport::price).sum::<usize>();
+            assert_eq!(price, one(&parse(input)));
+            let discounted = map.regions.iter().map(RegionReport::discounted_price);
+            assert_eq!(discounted.sum::<usize>(), two(&parse(input)));
+        }
+    }
+
+    #[test]
+    fn nested_regions() {
+        let map = regions(include_str!("test_medium.txt"));
+        let outer = map.region_at(0, 0);
+        assert_eq!((outer.plant, outer.area, outer.perimeter), (b'O', 21, 36));
+        assert_eq!(outer.holes, 4);
+        assert_eq!(outer.enclosed.len(), 4);
+        assert!(map.region_at(1, 1).enclosed.is_empty());
+
+        let map = regions(include_str!("test_abba.txt"));
+        let a = map.region_at(0, 0);
+        assert_eq!((a.sides, a.holes), (12, 2));
+        let b = map.region_at(3, 1);
+        assert_eq!(a.enclosed, vec![b.id, map.region_at(1, 3).id]);
+        assert_eq!(
+            b.bounds,
+            BoundingBox {
+                min_x: 3,
+                min_y: 1,
+                max_x: 4,
+                max_y: 2
+            }
+        );
+        assert_eq!(b.mask(), vec![true; 4]);
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::ops::Range;
+
+use super::Plant;
+
+/// A region whose plots have all been read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedRegion {
+    pub plant: Plant,
+    pub area: usize,
+    pub perimeter: usize,
+    pub sides: usize,
+    pub rows: Range<usize>,
+}
+
+impl CompletedRegion {
+    pub fn price(&self) -> usize {
+        self.area * self.perimeter
+    }
+
+    pub fn discounted_price(&self) -> usize {
+        self.area * self.sides
+    }
+}
+
+type SlotId = usize;
+
+#[derive(Debug, Clone)]
+struct Slot {
+    parent: SlotId,
+    plant: Plant,
+    area: usize,
+    perimeter: usize,
+    sides: usize,
+    first_row: usize,
+}
+
+/// Labels a garden one row at a time, keeping only the previous row and the regions touching it.
+/// Slots of merged and completed regions are recycled, so memory stays `O(width)`.
+pub struct RegionStream<R> {
+    reader: R,
+    line: String,
+    row: usize,
+    plants: Vec<Plant>,
+    labels: Vec<SlotId>,
+    slots: Vec<Slot>,
+    free: Vec<SlotId>,
+    allocated: Vec<SlotId>,
+    live: Vec<bool>,
+    ready: VecDeque<CompletedRegion>,
+    done: bool,
+}
+
+impl<R: BufRead> RegionStream<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            row: 0,
+            plants: Vec::new(),
+            labels: Vec::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            allocated: Vec::new(),
+            live: Vec::new(),
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn allocate(&mut self, plant: Plant) -> SlotId {
+        let slot = Slot {
+            parent: 0,
+            plant,
+            area: 0,
+            perimeter: 0,
+            sides: 0,
+            first_row: self.row,
+        };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.slots[id] = slot;
+                id
+            }
+            None => {
+                self.slots.push(slot);
+                self.live.push(false);
+                self.slots.len() - 1
+            }
+        };
+        self.slots[id].parent = id;
+        self.allocated.push(id);
+        id
+    }
+
+    fn find(&mut self, mut id: SlotId) -> SlotId {
+        while self.slots[id].parent != id {
+            let grandparent = self.slots[self.slots[id].parent].parent;
+            self.slots[id].parent = grandparent;
+            id = grandparent;
+        }
+        id
+    }
+
+    fn union(&mut self, a: SlotId, b: SlotId) -> SlotId {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            let from = self.slots[b].clone();
+            let into = &mut self.slots[a];
+            into.area += from.area;
+            into.perimeter += from.perimeter;
+            into.sides += from.sides;
+            into.first_row = into.first_row.min(from.first_row);
+            self.slots[b].parent = a;
+        }
+        a
+    }
+
+    fn label_row(&mut self, row: &[Plant]) -> Vec<SlotId> {
+        let mut labels: Vec<SlotId> = Vec::with_capacity(row.len());
+        for (x, &plant) in row.iter().enumerate() {
+            let north = (self.plants.get(x) == Some(&plant)).then(|| self.labels[x]);
+            let west = (x > 0 && row[x - 1] == plant).then(|| labels[x - 1]);
+            let id = match (north, west) {
+                (Some(north), Some(west)) => self.union(north, west),
+                (Some(id), None) | (None, Some(id)) => self.find(id),
+                (None, None) => self.allocate(plant),
+            };
+            self.slots[id].area += 1;
+            // West and east borders and the edges between neighbours in this row.
+            if west.is_none() {
+                self.slots[id].perimeter += 1;
+                if x > 0 {
+                    let west = self.find(labels[x - 1]);
+                    self.slots[west].perimeter += 1;
+                }
+            }
+            labels.push(id);
+        }
+        if let Some(&last) = labels.last() {
+            let last = self.find(last);
+            self.slots[last].perimeter += 1;
+        }
+        labels.iter().map(|&id| self.find(id)).collect()
+    }
+
+    /// Accounts for the horizontal fence between two rows and the corners on it.
+    fn boundary(&mut self, above: (&[Plant], &[SlotId]), below: (&[Plant], &[SlotId])) {
+        let width = above.0.len().max(below.0.len());
+        for x in 0..width {
+            let (a, b) = (above.0.get(x), below.0.get(x));
+            if a != b {
+                for (plants, labels) in [above, below] {
+                    if !plants.is_empty() {
+                        let id = self.find(labels[x]);
+                        self.slots[id].perimeter += 1;
+                    }
+                }
+            }
+        }
+        // Each 2x2 window is indexed `[nw, ne, sw, se]`, so `i ^ 1` is the horizontal
+        // neighbour of cell `i`, `i ^ 2` the vertical and `i ^ 3` the diagonal one.
+        for x in 0..=width {
+            let cell = |(plants, labels): (&[Plant], &[SlotId]), x: Option<usize>| {
+                x.and_then(|x| Some((*plants.get(x)?, labels[x])))
+            };
+            let window = [
+                cell(above, x.checked_sub(1)),
+                cell(above, Some(x)),
+                cell(below, x.checked_sub(1)),
+                cell(below, Some(x)),
+            ];
+            for (i, &cell) in window.iter().enumerate() {
+                let Some((plant, id)) = cell else { continue };
+                let same = |j: usize| window[j].map(|(plant, _)| plant) == Some(plant);
+                if same(i ^ 1) == same(i ^ 2) && !(same(i ^ 1) && same(i ^ 3)) {
+                    let id = self.find(id);
+                    self.slots[id].sides += 1;
+                }
+            }
+        }
+    }
+
+    /// Emits every region without plots in the latest row and recycles all slots not in use.
+    fn retire(&mut self, labels: &[SlotId]) {
+        for &id in labels {
+            self.live[id] = true;
+        }
+        let mut allocated = std::mem::take(&mut self.allocated);
+        allocated.retain(|&id| {
+            if self.live[id] {
+                return true;
+            }
+            let slot = &self.slots[id];
+            if slot.parent == id {
+                self.ready.push_back(CompletedRegion {
+                    plant: slot.plant,
+                    area: slot.area,
+                    perimeter: slot.perimeter,
+                    sides: slot.sides,
+                    rows: slot.first_row..self.row,
+                });
+            }
+            self.free.push(id);
+            false
+        });
+        for &id in labels {
+            self.live[id] = false;
+        }
+        self.allocated = allocated;
+    }
+
+    fn push_row(&mut self, row: &[Plant]) -> io::Result<()> {
+        if self.row > 0 && row.len() != self.plants.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("row {} has a different width", self.row),
+            ));
+        }
+        let labels = self.label_row(row);
+        let plants = std::mem::take(&mut self.plants);
+        let previous = std::mem::take(&mut self.labels);
+        self.boundary((&plants, &previous), (row, &labels));
+        self.retire(&labels);
+        self.row += 1;
+        self.plants = row.to_vec();
+        self.labels = labels;
+        Ok(())
+    }
+
+    fn finish(&mut self) {
+        let plants = std::mem::take(&mut self.plants);
+        let labels = std::mem::take(&mut self.labels);
+        self.boundary((&plants, &labels), (&[], &[]));
+        self.retire(&[]);
+    }
+}
+
+impl<R: BufRead> Iterator for RegionStream<R> {
+    type Item = io::Result<CompletedRegion>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(region) = self.ready.pop_front() {
+                return Some(Ok(region));
+            }
+            if self.done {
+                return None;
+            }
+            self.line.clear();
+            let read = self.reader.read_line(&mut self.line).and_then(|read| {
+                let row = self.line.trim_end_matches(['\n', '\r']).as_bytes().to_vec();
+                if read == 0 || row.is_empty() {
+                    self.done = true;
+                    self.finish();
+                    Ok(())
+                } else {
+                    self.push_row(&row)
+                }
+            });
+            if let Err(error) = read {
+                self.done = true;
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day12::{one, parse, two};
+
+    #[test]
+    fn matches_prices() {
+        for input in [
+            include_str!("test.txt"),
+            include_str!("test_small.txt"),
+            include_str!("test_medium.txt"),
+            include_str!("test_e.txt"),
+            include_str!("test_abba.txt"),
+        ] {
+            let regions: Vec<CompletedRegion> = RegionStream::new(input.as_bytes())
+                .collect::<io::Result<_>>()
+                .unwrap();
+            let price = regions.iter().map(CompletedRegion::price).sum::<usize>();
+            assert_eq!(price, one(&parse(input)));
+            let discounted = regions.iter().map(CompletedRegion::discounted_price);
+            assert_eq!(discounted.sum::<usize>(), two(&parse(input)));
+        }
+    }
+
+    #[test]
+    fn bounded_state() {
+        let garden = "ABAB\nAAAB\nCCAB\n".repeat(1000);
+        let mut stream = RegionStream::new(garden.as_bytes());
+        let first = stream.next().unwrap().unwrap();
+        assert_eq!((first.plant, first.area, first.rows), (b'B', 1, 0..1));
+        assert_eq!(stream.row, 2);
+        let rest: Vec<CompletedRegion> = stream.by_ref().map(Result::unwrap).collect();
+        assert_eq!(
+            rest.iter().map(|region| region.area).sum::<usize>(),
+            12000 - 1
+        );
+        assert!(stream.slots.len() <= 8);
+    }
+
+    #[test]
+    fn ragged_rows() {
+        let mut stream = RegionStream::new("AA\nA\n".as_bytes());
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().is_none());
+    }
+}
@@ -0,0 +1,303 @@
+use pathfinding::directed::astar::astar;
+use rustc_hash::FxHashMap;
+
+use super::warehouse::{MoveOutcome, Shape, Warehouse};
+use super::{CrateId, Facing, Object, Point};
+
+const FACINGS: [Facing; 4] = [Facing::North, Facing::South, Facing::West, Facing::East];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Goal {
+    /// Crate `i` ends with its top left corner at `origins[i]`. Crates of the same shape are
+    /// interchangeable.
+    Arrangement(Vec<Point>),
+    Gps(u32),
+}
+
+type State = (Point, Vec<Point>);
+
+/// States a search expands before giving up, unless set with [`Solver::with_limit`].
+const DEFAULT_LIMIT: usize = 1_000_000;
+
+/// Sokoban-style search for the shortest move sequence from a warehouse to a goal.
+pub struct Solver<'w> {
+    warehouse: &'w Warehouse,
+    /// Crates grouped by shape, so that states differing only by a permutation within a group
+    /// are stored once.
+    classes: Vec<Vec<CrateId>>,
+    limit: usize,
+}
+
+impl<'w> Solver<'w> {
+    pub fn new(warehouse: &'w Warehouse) -> Self {
+        let mut classes: Vec<Vec<CrateId>> = Vec::new();
+        let crates = warehouse.crates();
+        for (id, (_, shape)) in crates.iter().enumerate() {
+            match classes
+                .iter_mut()
+                .find(|class| crates[class[0]].1 == *shape)
+            {
+                Some(class) => class.push(id),
+                None => classes.push(vec![id]),
+            }
+        }
+        Self {
+            warehouse,
+            classes,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+
+    /// Gives up after expanding `limit` states.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    fn shape(&self, id: CrateId) -> &Shape {
+        &self.warehouse.crates()[id].1
+    }
+
+    fn canonical(&self, mut origins: Vec<Point>) -> Vec<Point> {
+        for class in &self.classes {
+            let mut sorted: Vec<Point> = class.iter().map(|&id| origins[id]).collect();
+            sorted.sort_unstable();
+            for (&id, origin) in class.iter().zip(sorted) {
+                origins[id] = origin;
+            }
+        }
+        origins
+    }
+
+    fn is_wall(&self, point: Point, facing: Facing) -> bool {
+        let outside = match facing {
+            Facing::North => point.1 == 0,
+            Facing::South => point.1 + 1 >= self.warehouse.height(),
+            Facing::West => point.0 == 0,
+            Facing::East => point.0 + 1 >= self.warehouse.width(),
+        };
+        outside || self.warehouse.object(facing.step(point)) == Some(Object::Wall)
+    }
+
+    /// Whether walls alone keep the crate from ever moving again: in each direction either
+    /// its front is walled, or there is no free cell behind it to push from.
+    fn is_frozen(&self, id: CrateId, origin: Point) -> bool {
+        let shape = self.shape(id);
+        let cells: Vec<Point> = shape.cells_at(origin).collect();
+        FACINGS.iter().all(|&facing| {
+            let back = facing.reverse();
+            let walled = cells.iter().any(|&cell| self.is_wall(cell, facing));
+            let pushable = cells
+                .iter()
+                .any(|&cell| !self.is_wall(cell, back) && !cells.contains(&back.step(cell)));
+            walled || !pushable
+        })
+    }
+
+    fn is_deadlocked(&self, origins: &[Point], goal: &[Point]) -> bool {
+        self.classes.iter().any(|class| {
+            class.iter().any(|&id| {
+                let goals = || class.iter().map(|&goal_id| goal[goal_id]);
+                !goals().any(|goal| goal == origins[id]) && self.is_frozen(id, origins[id])
+            })
+        })
+    }
+
+    /// Lowest and highest GPS coordinate crate `id` could ever contribute: anywhere it fits
+    /// between the walls, or where it stands if it can't move at all.
+    fn gps_range(&self, id: CrateId) -> (u32, u32) {
+        let gps = |origin: Point| 100 * origin.1 as u32 + origin.0 as u32;
+        let origin = self.warehouse.crates()[id].0;
+        if self.is_frozen(id, origin) {
+            return (gps(origin), gps(origin));
+        }
+        let (width, height) = (self.warehouse.width(), self.warehouse.height());
+        let fits = |origin: Point| {
+            self.shape(id).cells_at(origin).all(|cell| {
+                cell.0 < width
+                    && cell.1 < height
+                    && self.warehouse.object(cell) != Some(Object::Wall)
+            })
+        };
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| Point(x, y)))
+            .filter(|&origin| fits(origin))
+            .fold((u32::MAX, 0), |(low, high), origin| {
+                (low.min(gps(origin)), high.max(gps(origin)))
+            })
+    }
+
+    /// Lower bound on the moves left: every crate has to reach a goal of its shape and moves
+    /// at most one cell per step.
+    fn distance(&self, origins: &[Point], goal: &[Point]) -> usize {
+        self.classes
+            .iter()
+            .flat_map(|class| {
+                class.iter().map(|&id| {
+                    class
+                        .iter()
+                        .map(|&goal_id| {
+                            let (a, b) = (origins[id], goal[goal_id]);
+                            (a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as usize
+                        })
+                        .min()
+                        .unwrap()
+                })
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// A shortest list of moves reaching `goal`, or `None` if it can't be reached within the
+    /// limit.
+    pub fn solve(&self, goal: &Goal) -> Option<Vec<Facing>> {
+        let target = match goal {
+            Goal::Arrangement(origins) => {
+                assert_eq!(origins.len(), self.warehouse.crates().len());
+                Some(self.canonical(origins.clone()))
+            }
+            Goal::Gps(_) => None,
+        };
+        let start: State = (
+            self.warehouse.robot(),
+            self.canonical(self.warehouse.crates().iter().map(|c| c.0).collect()),
+        );
+        if target
+            .as_ref()
+            .is_some_and(|target| self.is_deadlocked(&start.1, target))
+        {
+            return None;
+        }
+        if let Goal::Gps(gps) = goal {
+            let (low, high) = (0..self.warehouse.crates().len())
+                .map(|id| self.gps_range(id))
+                .fold((0, 0), |(low, high), range| (low + range.0, high + range.1));
+            if !(low..=high).contains(gps) {
+                return None;
+            }
+        }
+
+        let mut scratch = self.warehouse.clone();
+        // The facing of every robot move seen, by the cells the robot moved between.
+        let mut moves: FxHashMap<(Point, Point), Facing> = FxHashMap::default();
+        let mut expanded = 0;
+        let (path, _) = astar(
+            &start,
+            |(robot, origins)| {
+                expanded += 1;
+                if expanded > self.limit {
+                    return Vec::new();
+                }
+                scratch.rearrange(*robot, origins);
+                let mut successors = Vec::new();
+                for facing in FACINGS {
+                    if scratch.step(facing) == MoveOutcome::Blocked {
+                        scratch.undo();
+                        continue;
+                    }
+                    let origins = self.canonical(scratch.crates().iter().map(|c| c.0).collect());
+                    let next = scratch.robot();
+                    scratch.undo();
+                    if target
+                        .as_ref()
+                        .is_some_and(|target| self.is_deadlocked(&origins, target))
+                    {
+                        continue;
+                    }
+                    moves.insert((*robot, next), facing);
+                    successors.push(((next, origins), 1));
+                }
+                successors
+            },
+            |(_, origins)| target.as_ref().map_or(0, |t| self.distance(origins, t)),
+            |(_, origins)| match (goal, &target) {
+                (Goal::Gps(gps), _) => {
+                    let total: u32 = origins.iter().map(|o| 100 * o.1 as u32 + o.0 as u32).sum();
+                    total == *gps
+                }
+                (Goal::Arrangement(_), target) => Some(origins) == target.as_ref(),
+            },
+        )?;
+        Some(
+            path.windows(2)
+                .map(|pair| moves[&(pair[0].0, pair[1].0)])
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(map: &str, goal: Goal, moves: usize) {
+        let warehouse = Warehouse::from_map(map);
+        let solution = Solver::new(&warehouse).solve(&goal).unwrap();
+        assert_eq!(solution.len(), moves);
+        let mut replay = warehouse.clone();
+        replay.run(&solution);
+        match goal {
+            Goal::Gps(gps) => assert_eq!(replay.gps(), gps),
+            Goal::Arrangement(origins) => {
+                let mut reached: Vec<Point> = replay.crates().iter().map(|c| c.0).collect();
+                let mut origins = origins;
+                reached.sort_unstable();
+                origins.sort_unstable();
+                assert_eq!(reached, origins);
+            }
+        }
+    }
+
+    #[test]
+    fn push_into_place() {
+        let map = "#######\n#@.O..#\n#.....#\n#######";
+        check(map, Goal::Arrangement(vec![Point(5, 1)]), 3);
+        check(map, Goal::Gps(104), 2);
+        check(map, Goal::Gps(103), 0);
+    }
+
+    #[test]
+    fn interchangeable_crates() {
+        let map = "########\n#@O..O.#\n#......#\n########";
+        check(map, Goal::Arrangement(vec![Point(6, 1), Point(3, 1)]), 6);
+        check(
+            "#######\n#.....#\n#.[]..#\n#..@..#\n#######",
+            Goal::Gps(103),
+            5,
+        );
+    }
+
+    #[test]
+    fn deadlocks() {
+        let warehouse = Warehouse::from_map("######\n#O.@.#\n#....#\n######");
+        let solver = Solver::new(&warehouse);
+        assert_eq!(solver.solve(&Goal::Arrangement(vec![Point(2, 1)])), None);
+        let warehouse = Warehouse::from_map("######\n#.O@.#\n#....#\n######");
+        let solver = Solver::new(&warehouse);
+        assert_eq!(solver.solve(&Goal::Arrangement(vec![Point(2, 2)])), None);
+        assert_eq!(
+            solver.solve(&Goal::Arrangement(vec![Point(1, 1)])),
+            Some(vec![Facing::West])
+        );
+    }
+
+    #[test]
+    fn without_border_walls() {
+        check("@O..\n....", Goal::Arrangement(vec![Point(3, 0)]), 2);
+        check(".O.\n.@.", Goal::Gps(1), 0);
+    }
+
+    #[test]
+    fn unreachable_gps() {
+        let warehouse = Warehouse::from_map("######\n#@O..#\n#....#\n######");
+        let solver = Solver::new(&warehouse);
+        assert_eq!(solver.solve(&Goal::Gps(500)), None);
+        assert_eq!(solver.solve(&Goal::Gps(201)), None);
+        assert_eq!(
+            solver.solve(&Goal::Gps(104)).map(|moves| moves.len()),
+            Some(2)
+        );
+        let limited = Solver::new(&warehouse).with_limit(2);
+        assert_eq!(limited.solve(&Goal::Gps(104)), None);
+    }
+}
@@ -0,0 +1,392 @@
+use std::fmt::{self, Display};
+
+use rustc_hash::FxHashMap;
+
+use super::{parse, parse2, Coord, CrateId, Facing, Map, Object, Point};
+
+/// The cells a crate covers, relative to the top left corner of its bounding box.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shape {
+    cells: Vec<(Coord, Coord)>,
+}
+
+impl Shape {
+    /// A shape covering the given cells, which may be offset arbitrarily.
+    pub fn new(cells: impl IntoIterator<Item = (Coord, Coord)>) -> Self {
+        let mut cells: Vec<(Coord, Coord)> = cells.into_iter().collect();
+        let min_x = cells
+            .iter()
+            .map(|c| c.0)
+            .min()
+            .expect("shapes cover a cell");
+        let min_y = cells.iter().map(|c| c.1).min().unwrap();
+        for cell in cells.iter_mut() {
+            *cell = (cell.0 - min_x, cell.1 - min_y);
+        }
+        cells.sort_unstable_by_key(|&(x, y)| (y, x));
+        cells.dedup();
+        Self { cells }
+    }
+
+    /// A single row of `width` cells.
+    pub fn wide(width: Coord) -> Self {
+        Self::new((0..width).map(|x| (x, 0)))
+    }
+
+    pub fn cells(&self) -> &[(Coord, Coord)] {
+        &self.cells
+    }
+
+    pub fn cells_at(&self, origin: Point) -> impl Iterator<Item = Point> + '_ {
+        self.cells
+            .iter()
+            .map(move |&(x, y)| Point(origin.0 + x, origin.1 + y))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveOutcome {
+    Moved,
+    Blocked,
+    Pushed(usize),
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    facing: Facing,
+    outcome: MoveOutcome,
+    pushed: Vec<CrateId>,
+}
+
+/// A warehouse holding crates of any shape, which is moved through one robot step at a time
+/// and remembers its history. Cells outside the map behave like walls.
+#[derive(Debug, Clone)]
+pub struct Warehouse {
+    map: Map<Option<Object>>,
+    crates: Vec<(Point, Shape)>,
+    robot: Point,
+    history: Vec<Step>,
+}
+
+/// The part 1 warehouse and the robot's moves.
+pub fn parse_warehouse(puzzle: &str) -> (Warehouse, Vec<Facing>) {
+    let input = parse(puzzle);
+    (input.warehouse, input.moves)
+}
+
+/// The part 2 warehouse, twice as wide, and the robot's moves.
+pub fn parse_wide_warehouse(puzzle: &str) -> (Warehouse, Vec<Facing>) {
+    let input = parse2(puzzle);
+    (input.warehouse, input.moves)
+}
+
+impl Warehouse {
+    pub fn new(width: Coord, height: Coord) -> Self {
+        Self {
+            map: Map {
+                data: vec![None; width as usize * height as usize],
+                width,
+                height,
+            },
+            crates: Vec::new(),
+            robot: Point(0, 0),
+            history: Vec::new(),
+        }
+    }
+
+    /// Reads a rendered warehouse. Besides `#`, `.`, `@`, `O` and `[]`, every letter marks a
+    /// cell of a crate formed by all cells with the same letter.
+    pub fn from_map(map: &str) -> Self {
+        let lines: Vec<&[u8]> = map.lines().map(str::as_bytes).collect();
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+        let mut warehouse = Self::new(width as Coord, lines.len() as Coord);
+        let mut lettered: FxHashMap<u8, Vec<(Coord, Coord)>> = FxHashMap::default();
+        for (y, line) in lines.iter().enumerate() {
+            for (x, &ch) in line.iter().enumerate() {
+                let point = Point(x as Coord, y as Coord);
+                match ch {
+                    b'#' => warehouse.add_wall(point),
+                    b'O' => {
+                        warehouse.add_crate(point, Shape::wide(1));
+                    }
+                    b'[' => {
+                        warehouse.add_crate(point, Shape::wide(2));
+                    }
+                    b'@' => warehouse.set_robot(point),
+                    b'a'..=b'z' | b'A'..=b'Z' => {
+                        lettered.entry(ch).or_default().push((point.0, point.1))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let mut lettered: Vec<_> = lettered.into_values().collect();
+        lettered.sort_unstable();
+        for cells in lettered {
+            let origin = Point(
+                cells.iter().map(|c| c.0).min().unwrap(),
+                cells.iter().map(|c| c.1).min().unwrap(),
+            );
+            warehouse.add_crate(origin, Shape::new(cells));
+        }
+        warehouse
+    }
+
+    pub fn width(&self) -> Coord {
+        self.map.width
+    }
+
+    pub fn height(&self) -> Coord {
+        self.map.height
+    }
+
+    pub fn add_wall(&mut self, point: Point) {
+        self.map.set(&point, Some(Object::Wall));
+    }
+
+    /// Places a crate with the top left corner of its bounding box at `origin`, unless it
+    /// would overlap anything or stick out of the map.
+    pub fn add_crate(&mut self, origin: Point, shape: Shape) -> Option<CrateId> {
+        let id = self.crates.len();
+        let free = shape.cells_at(origin).all(|cell| {
+            cell.0 < self.map.width && cell.1 < self.map.height && self.object(cell).is_none()
+        });
+        if !free {
+            return None;
+        }
+        for cell in shape.cells_at(origin) {
+            self.map.set(&cell, Some(Object::Crate(id)));
+        }
+        self.crates.push((origin, shape));
+        Some(id)
+    }
+
+    /// Moves the robot and every crate `i` to `origins[i]` at once.
+    pub fn rearrange(&mut self, robot: Point, origins: &[Point]) {
+        let crates: Vec<CrateId> = (0..self.crates.len()).collect();
+        for &id in &crates {
+            let (origin, shape) = &self.crates[id];
+            for cell in shape.cells_at(*origin) {
+                self.map.set(&cell, None);
+            }
+        }
+        for (id, &target) in crates.into_iter().zip(origins) {
+            let (origin, shape) = &mut self.crates[id];
+            *origin = target;
+            for cell in shape.cells_at(target) {
+                self.map.set(&cell, Some(Object::Crate(id)));
+            }
+        }
+        self.robot = robot;
+    }
+
+    pub fn set_robot(&mut self, point: Point) {
+        self.robot = point;
+    }
+
+    pub fn robot(&self) -> Point {
+        self.robot
+    }
+
+    pub fn object(&self, point: Point) -> Option<Object> {
+        *self.map.get(&point)
+    }
+
+    /// Positions and shapes of all crates, indexed by [`CrateId`].
+    pub fn crates(&self) -> &[(Point, Shape)] {
+        &self.crates
+    }
+
+    fn ahead(&self, point: Point, facing: Facing) -> Option<Point> {
+        let blocked = match facing {
+            Facing::North => point.1 == 0,
+            Facing::South => point.1 + 1 >= self.map.height,
+            Facing::West => point.0 == 0,
+            Facing::East => point.0 + 1 >= self.map.width,
+        };
+        (!blocked).then(|| facing.step(point))
+    }
+
+    /// Crates which would be pushed by moving towards `facing`, or `None` if a wall is in the
+    /// way.
+    fn pushed_crates(&self, facing: Facing) -> Option<Vec<CrateId>> {
+        let mut pushed = Vec::new();
+        let mut frontier = vec![self.ahead(self.robot, facing)?];
+        while let Some(point) = frontier.pop() {
+            match self.object(point) {
+                None => {}
+                Some(Object::Wall) => return None,
+                Some(Object::Crate(id)) if pushed.contains(&id) => {}
+                Some(Object::Crate(id)) => {
+                    pushed.push(id);
+                    let (origin, shape) = &self.crates[id];
+                    for cell in shape.cells_at(*origin) {
+                        frontier.push(self.ahead(cell, facing)?);
+                    }
+                }
+            }
+        }
+        Some(pushed)
+    }
+
+    fn shift(&mut self, crates: &[CrateId], facing: Facing) {
+        for &id in crates {
+            let (origin, shape) = &self.crates[id];
+            for cell in shape.cells_at(*origin) {
+                self.map.set(&cell, None);
+            }
+        }
+        for &id in crates {
+            let (origin, shape) = &mut self.crates[id];
+            *origin = facing.step(*origin);
+            for cell in shape.cells_at(*origin) {
+                self.map.set(&cell, Some(Object::Crate(id)));
+            }
+        }
+    }
+
+    pub fn step(&mut self, facing: Facing) -> MoveOutcome {
+        let (outcome, pushed) = match self.pushed_crates(facing) {
+            None => (MoveOutcome::Blocked, Vec::new()),
+            Some(pushed) if pushed.is_empty() => (MoveOutcome::Moved, pushed),
+            Some(pushed) => {
+                self.shift(&pushed, facing);
+                (MoveOutcome::Pushed(pushed.len()), pushed)
+            }
+        };
+        if outcome != MoveOutcome::Blocked {
+            self.robot = facing.step(self.robot);
+        }
+        self.history.push(Step {
+            facing,
+            outcome,
+            pushed,
+        });
+        outcome
+    }
+
+    pub fn run(&mut self, moves: &[Facing]) {
+        for &facing in moves {
+            self.step(facing);
+        }
+    }
+
+    /// Reverts the latest step, returning its direction.
+    pub fn undo(&mut self) -> Option<Facing> {
+        let step = self.history.pop()?;
+        let back = step.facing.reverse();
+        if step.outcome != MoveOutcome::Blocked {
+            self.robot = back.step(self.robot);
+        }
+        self.shift(&step.pushed, back);
+        Some(step.facing)
+    }
+
+    /// Every step taken so far with its outcome.
+    pub fn history(&self) -> impl Iterator<Item = (Facing, MoveOutcome)> + '_ {
+        self.history.iter().map(|step| (step.facing, step.outcome))
+    }
+
+    /// Sum of the GPS coordinates of the top left corners of all crates.
+    pub fn gps(&self) -> u32 {
+        self.crates
+            .iter()
+            .map(|(origin, _)| 100 * origin.1 as u32 + origin.0 as u32)
+            .sum()
+    }
+}
+
+impl Display for Warehouse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.map.height {
+            for x in 0..self.map.width {
+                let point = Point(x, y);
+                let symbol = match self.object(point) {
+                    Some(Object::Wall) => '#',
+                    Some(Object::Crate(id)) => {
+                        let (origin, shape) = &self.crates[id];
+                        match shape.cells() {
+                            [_] => 'O',
+                            [(0, 0), (1, 0)] if point == *origin => '[',
+                            [(0, 0), (1, 0)] => ']',
+                            _ => (b'a' + (id % 26) as u8) as char,
+                        }
+                    }
+                    None if point == self.robot => '@',
+                    None => '.',
+                };
+                write!(f, "{symbol}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay(mut warehouse: Warehouse, moves: &[Facing]) -> u32 {
+        let start = warehouse.to_string();
+        warehouse.run(moves);
+        let gps = warehouse.gps();
+        assert_eq!(warehouse.history().count(), moves.len());
+        while warehouse.undo().is_some() {}
+        assert_eq!(warehouse.to_string(), start);
+        gps
+    }
+
+    #[test]
+    fn replay_and_undo() {
+        let (warehouse, moves) = parse_warehouse(include_str!("test.txt"));
+        assert_eq!(replay(warehouse, &moves), 10092);
+        let (warehouse, moves) = parse_wide_warehouse(include_str!("test.txt"));
+        let rendered = warehouse.to_string();
+        assert_eq!(Warehouse::from_map(&rendered).to_string(), rendered);
+        assert_eq!(replay(warehouse, &moves), 9021);
+    }
+
+    #[test]
+    fn outcomes() {
+        let (mut warehouse, _) = parse_warehouse(include_str!("small.txt"));
+        assert_eq!(warehouse.step(Facing::West), MoveOutcome::Blocked);
+        assert_eq!(warehouse.step(Facing::North), MoveOutcome::Moved);
+        assert_eq!(warehouse.step(Facing::East), MoveOutcome::Pushed(1));
+        assert_eq!(warehouse.step(Facing::East), MoveOutcome::Pushed(2));
+        assert_eq!(warehouse.step(Facing::East), MoveOutcome::Blocked);
+        assert_eq!(warehouse.undo(), Some(Facing::East));
+        assert_eq!(warehouse.undo(), Some(Facing::East));
+        assert_eq!(warehouse.robot(), Point(3, 1));
+        assert_eq!(warehouse.to_string().lines().nth(1), Some("#..@OO.#"));
+    }
+
+    #[test]
+    fn shaped_crates() {
+        let mut warehouse = Warehouse::from_map(
+            "\
+#######
+#.....#
+#.a...#
+#.aab.#
+#..@b.#
+#######",
+        );
+        // The L is lifted until it hits the wall, the upright bar is shoved aside in one piece.
+        assert_eq!(warehouse.step(Facing::North), MoveOutcome::Pushed(1));
+        assert_eq!(warehouse.step(Facing::North), MoveOutcome::Blocked);
+        assert_eq!(warehouse.step(Facing::East), MoveOutcome::Pushed(1));
+        assert_eq!(warehouse.step(Facing::North), MoveOutcome::Moved);
+        assert_eq!(
+            warehouse.to_string(),
+            "\
+#######
+#.a...#
+#.aa@.#
+#....b#
+#....b#
+#######
+"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::ops::Range;
+
+use rustc_hash::FxHashSet;
+
+use super::{parse_alt, Coord, Facing, Grid2D, MazeCosts, Num};
+
+const FACINGS: [Facing; 4] = [Facing::North, Facing::South, Facing::West, Facing::East];
+
+type State = usize;
+
+/// A walk from one junction to the next without any choice on the way.
+#[derive(Debug, Clone)]
+struct Corridor {
+    to: State,
+    cost: Num,
+    /// The tiles strictly between the two junctions, as a range of `CorridorGraph::tiles`.
+    tiles: Range<usize>,
+}
+
+/// The maze reduced to its junctions, the start and the end. Straight corridors and bends
+/// become single weighted edges and dead ends are dropped.
+#[derive(Debug, Clone)]
+pub struct CorridorGraph {
+    /// Tile index of each junction; junction `j` facing `f` is state `4 * j + f`.
+    junctions: Vec<usize>,
+    corridors: Vec<Vec<Corridor>>,
+    tiles: Vec<usize>,
+    costs: MazeCosts,
+    start: State,
+    end: usize,
+}
+
+/// Cheapest way to turn by `n` quarter turns to the left, for `n` from 0 to 3, chaining the
+/// single turns. Inside a corridor only the resulting facing matters.
+fn rotations(costs: &MazeCosts) -> [Num; 4] {
+    let turns = [
+        Some((1, costs.turn_left)),
+        Some((3, costs.turn_right)),
+        costs.u_turn().map(|cost| (2, cost)),
+    ];
+    let mut rotations = [0, Num::MAX, Num::MAX, Num::MAX];
+    for _ in 0..3 {
+        for from in 0..4 {
+            for &(by, cost) in turns.iter().flatten() {
+                let to = (from + by) % 4;
+                rotations[to] = rotations[to].min(rotations[from].saturating_add(cost));
+            }
+        }
+    }
+    rotations
+}
+
+impl CorridorGraph {
+    pub fn junctions(&self) -> usize {
+        self.junctions.len()
+    }
+
+    /// Turning on the spot at the junction of `state`.
+    fn turns(&self, state: State) -> impl Iterator<Item = (State, Num)> {
+        let (junction, facing) = (state / 4, FACINGS[state % 4]);
+        [
+            Some((facing.turn_left(), self.costs.turn_left)),
+            Some((facing.turn_right(), self.costs.turn_right)),
+            self.costs.u_turn().map(|cost| (facing.reverse(), cost)),
+        ]
+        .into_iter()
+        .flatten()
+        .map(move |(turned, cost)| (junction * 4 + turned as usize, cost))
+    }
+
+    fn successors(&self, state: State) -> impl Iterator<Item = (State, Num)> + '_ {
+        self.turns(state).chain(
+            self.corridors[state]
+                .iter()
+                .map(|corridor| (corridor.to, corridor.cost)),
+        )
+    }
+
+    fn distances(&self) -> Vec<Num> {
+        let mut distances = vec![Num::MAX; self.corridors.len()];
+        distances[self.start] = 0;
+        let mut queue = BinaryHeap::from([Reverse((0, self.start))]);
+        while let Some(Reverse((distance, state))) = queue.pop() {
+            if distance > distances[state] {
+                continue;
+            }
+            if state / 4 == self.end {
+                break;
+            }
+            for (next, cost) in self.successors(state) {
+                if distance + cost < distances[next] {
+                    distances[next] = distance + cost;
+                    queue.push(Reverse((distance + cost, next)));
+                }
+            }
+        }
+        distances
+    }
+
+    fn best(&self, distances: &[Num]) -> Option<Num> {
+        let best = (0..4).map(|f| distances[self.end * 4 + f]).min().unwrap();
+        (best != Num::MAX).then_some(best)
+    }
+
+    /// The lowest score of any route, or `None` if the end cannot be reached.
+    pub fn lowest_score(&self) -> Option<Num> {
+        self.best(&self.distances())
+    }
+
+    /// Number of tiles on any lowest-score route, or `None` if the end cannot be reached.
+    pub fn best_tiles(&self) -> Option<usize> {
+        let mut distances = self.distances();
+        let best = self.best(&distances)?;
+        // The search stops once the end is settled, so states costing more than `best` may
+        // keep tentative distances, which are only upper bounds. No such state lies on a best
+        // route, so they are dropped instead of being matched against stale values.
+        for distance in &mut distances {
+            if *distance > best {
+                *distance = Num::MAX;
+            }
+        }
+
+        let mut predecessors: Vec<Vec<(State, Num, Option<&Corridor>)>> =
+            vec![Vec::new(); self.corridors.len()];
+        for (state, corridors) in self.corridors.iter().enumerate() {
+            if distances[state] == Num::MAX {
+                continue;
+            }
+            for (turned, cost) in self.turns(state) {
+                predecessors[turned].push((state, cost, None));
+            }
+            for corridor in corridors {
+                predecessors[corridor.to].push((state, corridor.cost, Some(corridor)));
+            }
+        }
+
+        let mut on_route = vec![false; self.corridors.len()];
+        let mut tiles = FxHashSet::default();
+        let mut stack: Vec<State> = (0..4)
+            .map(|f| self.end * 4 + f)
+            .filter(|&state| distances[state] == best)
+            .collect();
+        while let Some(state) = stack.pop() {
+            if std::mem::replace(&mut on_route[state], true) {
+                continue;
+            }
+            tiles.insert(self.junctions[state / 4]);
+            for &(previous, cost, corridor) in &predecessors[state] {
+                if distances[previous] + cost == distances[state] {
+                    if let Some(corridor) = corridor {
+                        tiles.extend(&self.tiles[corridor.tiles.clone()]);
+                    }
+                    stack.push(previous);
+                }
+            }
+        }
+        Some(tiles.len())
+    }
+}
+
+fn is_junction(grid: &Grid2D, tile: (Coord, Coord)) -> bool {
+    let exits = FACINGS
+        .iter()
+        .filter(|facing| {
+            let next = facing.advance(tile);
+            grid.is_passable(next.0, next.1)
+        })
+        .count();
+    exits > 2
+}
+
+pub fn corridor_graph(puzzle: &str, costs: &MazeCosts) -> CorridorGraph {
+    costs.check();
+    let rotations = rotations(costs);
+    let (grid, start, end) = parse_alt(puzzle.trim_end());
+    let mut junction_of = vec![usize::MAX; grid.data.len()];
+    let mut junctions = Vec::new();
+    for (index, &ch) in grid.data.iter().enumerate() {
+        if ch != b'#' && ch != b'\n' {
+            let tile = grid.to_point(index);
+            if tile == start || tile == end || is_junction(&grid, tile) {
+                junction_of[index] = junctions.len();
+                junctions.push(index);
+            }
+        }
+    }
+
+    let mut corridors = vec![Vec::new(); junctions.len() * 4];
+    let mut tiles = Vec::new();
+    for (junction, &index) in junctions.iter().enumerate() {
+        let origin = grid.to_point(index);
+        for leaving in FACINGS {
+            let mut tile = leaving.advance(origin);
+            if !grid.is_passable(tile.0, tile.1) {
+                continue;
+            }
+            let (mut facing, mut cost, first) = (leaving, costs.forward, tiles.len());
+            let arrived = loop {
+                let to = junction_of[grid.to_index(tile.0, tile.1)];
+                if to != usize::MAX {
+                    break Some(to * 4 + facing as usize);
+                }
+                tiles.push(grid.to_index(tile.0, tile.1));
+                let Some((turned, turn_cost)) = [
+                    (facing, 0),
+                    (facing.turn_left(), rotations[1]),
+                    (facing.turn_right(), rotations[3]),
+                ]
+                .into_iter()
+                .find(|(turned, _)| {
+                    let next = turned.advance(tile);
+                    grid.is_passable(next.0, next.1)
+                }) else {
+                    break None;
+                };
+                facing = turned;
+                cost += turn_cost + costs.forward;
+                tile = facing.advance(tile);
+            };
+            match arrived {
+                Some(to) => corridors[junction * 4 + leaving as usize].push(Corridor {
+                    to,
+                    cost,
+                    tiles: first..tiles.len(),
+                }),
+                None => tiles.truncate(first),
+            }
+        }
+    }
+
+    CorridorGraph {
+        start: junction_of[grid.to_index(start.0, start.1)] * 4 + Facing::East as usize,
+        end: junction_of[grid.to_index(end.0, end.1)],
+        junctions,
+        corridors,
+        tiles,
+        costs: *costs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day16::ranked::k_shortest;
+    use crate::day16::routes::optimal_routes;
+    use crate::day16::{part1, part2};
+
+    #[test]
+    fn matches_tile_graph() {
+        for input in [include_str!("test.txt"), include_str!("test2.txt")] {
+            let graph = corridor_graph(input, &MazeCosts::REINDEER);
+            assert_eq!(graph.lowest_score(), Some(part1(input)));
+            assert_eq!(graph.best_tiles(), Some(part2(input)));
+        }
+    }
+
+    #[test]
+    fn contracts_corridors() {
+        let maze = "#######\n#....E#\n#.###.#\n#S....#\n#######";
+        let graph = corridor_graph(maze, &MazeCosts::REINDEER);
+        assert_eq!(graph.junctions(), 2);
+        assert_eq!(graph.lowest_score(), Some(1006));
+        assert_eq!(graph.best_tiles(), Some(7));
+
+        let dead_ends = "#######\n#.#..E#\n#.#.#.#\n#S....#\n#######";
+        let graph = corridor_graph(dead_ends, &MazeCosts::REINDEER);
+        assert_eq!(graph.junctions(), 3);
+        assert_eq!(graph.lowest_score(), Some(part1(dead_ends)));
+        assert_eq!(graph.best_tiles(), Some(part2(dead_ends)));
+    }
+
+    #[test]
+    fn other_costs() {
+        let lopsided = MazeCosts {
+            forward: 2,
+            turn_left: 5,
+            turn_right: 3000,
+            u_turn: 2000,
+        };
+        let cheap_u_turn = MazeCosts {
+            u_turn: 300,
+            ..MazeCosts::REINDEER
+        };
+        for input in [
+            include_str!("test.txt"),
+            include_str!("test2.txt"),
+            "#######\n#.#..E#\n#.#.#.#\n#S....#\n#######",
+        ] {
+            for costs in [lopsided, cheap_u_turn] {
+                let graph = corridor_graph(input, &costs);
+                let routes = optimal_routes(input, &costs).unwrap();
+                assert_eq!(
+                    graph.lowest_score(),
+                    Some(k_shortest(input, &costs, 1)[0].cost)
+                );
+                assert_eq!(graph.lowest_score(), Some(routes.cost()));
+                assert_eq!(graph.best_tiles(), Some(routes.best_tiles().len()));
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_end() {
+        let graph = corridor_graph("#######\n#S.#.E#\n#######", &MazeCosts::REINDEER);
+        assert_eq!(graph.lowest_score(), None);
+        assert_eq!(graph.best_tiles(), None);
+    }
+}
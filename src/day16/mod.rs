@@ -7,12 +7,89 @@ use smallvec::{smallvec, SmallVec};
 
 use crate::util::first_line_length;
 
+pub mod corridors;
+pub mod ranked;
+pub mod routes;
+
 type Num = u32;
 type Input = (Graph<(), Num>, NodeIndex, [NodeIndex; 4], NodeIndex, usize);
 
+/// Price of each move the reindeer can make.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MazeCosts {
+    pub forward: Num,
+    pub turn_left: Num,
+    pub turn_right: Num,
+    /// Only used where it is cheaper than two quarter turns in the same direction.
+    pub u_turn: Num,
+}
+
+impl MazeCosts {
+    /// The costs from the puzzle.
+    pub const REINDEER: MazeCosts = MazeCosts {
+        forward: 1,
+        turn_left: 1000,
+        turn_right: 1000,
+        u_turn: 2000,
+    };
+
+    /// Panics unless every cost is positive.
+    pub fn new(forward: Num, turn_left: Num, turn_right: Num, u_turn: Num) -> Self {
+        let costs = Self {
+            forward,
+            turn_left,
+            turn_right,
+            u_turn,
+        };
+        costs.check();
+        costs
+    }
+
+    /// Free moves would let routes circle on a tile without getting any more expensive.
+    fn check(&self) {
+        assert!(
+            self.forward > 0 && self.turn_left > 0 && self.turn_right > 0 && self.u_turn > 0,
+            "maze costs must be positive: {self:?}"
+        );
+    }
+
+    fn u_turn(&self) -> Option<Num> {
+        (self.u_turn < 2 * self.turn_left.min(self.turn_right)).then_some(self.u_turn)
+    }
+
+    #[inline(always)]
+    fn successors(&self, grid: &Grid2D, Node(x, y, d): &Node) -> SmallVec<[(Node, Num); 4]> {
+        let mut next: SmallVec<[_; 4]> = smallvec![
+            (Node(*x, *y, d.turn_left()), self.turn_left),
+            (Node(*x, *y, d.turn_right()), self.turn_right),
+        ];
+        if let Some(u_turn) = self.u_turn() {
+            next.push((Node(*x, *y, d.reverse()), u_turn));
+        }
+        let forward = d.advance((*x, *y));
+        if grid.is_passable(forward.0, forward.1) {
+            next.push((Node(forward.0, forward.1, *d), self.forward));
+        }
+        next
+    }
+}
+
+impl Default for MazeCosts {
+    fn default() -> Self {
+        Self::REINDEER
+    }
+}
+
 #[aoc_generator(day16, part1, dijkstra)]
 #[aoc_generator(day16, part2, dijkstra)]
 fn parse(input: &str) -> Input {
+    parse_with_costs(input, &MazeCosts::REINDEER)
+}
+
+/// Builds the maze graph with four nodes per open tile, one per facing in the order north,
+/// east, south, west.
+fn parse_with_costs(input: &str, costs: &MazeCosts) -> Input {
+    costs.check();
     let input = input.as_bytes().trim_ascii();
     let mut maze = Graph::<(), Num>::new();
     let width = first_line_length(input);
@@ -56,27 +133,42 @@ fn parse(input: &str) -> Input {
             _ => unreachable!("invalid input character: {}", ch),
         }
 
+        let (left, right) = (costs.turn_left, costs.turn_right);
         maze.extend_with_edges([
-            (v_north, v_east, 1000),
-            (v_east, v_north, 1000),
-            (v_east, v_south, 1000),
-            (v_south, v_east, 1000),
-            (v_south, v_west, 1000),
-            (v_west, v_south, 1000),
-            (v_west, v_north, 1000),
-            (v_north, v_west, 1000),
+            (v_north, v_east, right),
+            (v_east, v_north, left),
+            (v_east, v_south, right),
+            (v_south, v_east, left),
+            (v_south, v_west, right),
+            (v_west, v_south, left),
+            (v_west, v_north, right),
+            (v_north, v_west, left),
         ]);
+        if let Some(u_turn) = costs.u_turn() {
+            maze.extend_with_edges([
+                (v_north, v_south, u_turn),
+                (v_south, v_north, u_turn),
+                (v_east, v_west, u_turn),
+                (v_west, v_east, u_turn),
+            ]);
+        }
 
         if row > 0 {
             if let Some(n_north) = node_list[v_idx_start - stride] {
                 let n_south = node_list[v_idx_start - stride + 2].unwrap();
-                maze.extend_with_edges([(v_north, n_north, 1), (n_south, v_south, 1)]);
+                maze.extend_with_edges([
+                    (v_north, n_north, costs.forward),
+                    (n_south, v_south, costs.forward),
+                ]);
             }
         }
         if col > 0 {
             if let Some(w_west) = node_list[v_idx_start - NODES_PER_SQUARE + 3] {
                 let w_east = node_list[v_idx_start - NODES_PER_SQUARE + 1].unwrap();
-                maze.extend_with_edges([(v_west, w_west, 1), (w_east, v_east, 1)]);
+                maze.extend_with_edges([
+                    (v_west, w_west, costs.forward),
+                    (w_east, v_east, costs.forward),
+                ]);
             }
         }
 
@@ -138,7 +230,7 @@ pub fn part2(puzzle: &str) -> usize {
     two(&parse(puzzle))
 }
 
-type Coord = u16;
+pub type Coord = u16;
 
 #[derive(Debug, Clone)]
 struct Grid2D {
@@ -212,8 +304,24 @@ fn parse_alt(puzzle: &str) -> InputPathfinding {
     (grid, start.unwrap(), end.unwrap())
 }
 
+#[aoc_generator(day16, part1, corridors)]
+#[aoc_generator(day16, part2, corridors)]
+fn parse_corridors(puzzle: &str) -> corridors::CorridorGraph {
+    corridors::corridor_graph(puzzle, &MazeCosts::REINDEER)
+}
+
+#[aoc(day16, part1, corridors)]
+fn one_corridors(graph: &corridors::CorridorGraph) -> Num {
+    graph.lowest_score().unwrap()
+}
+
+#[aoc(day16, part2, corridors)]
+fn two_corridors(graph: &corridors::CorridorGraph) -> usize {
+    graph.best_tiles().unwrap()
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Facing {
+pub enum Facing {
     North,
     South,
     West,
@@ -250,6 +358,16 @@ impl Facing {
             Facing::East => Facing::North,
         }
     }
+
+    #[inline]
+    fn reverse(&self) -> Facing {
+        match self {
+            Facing::North => Facing::South,
+            Facing::South => Facing::North,
+            Facing::West => Facing::East,
+            Facing::East => Facing::West,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -267,17 +385,7 @@ fn one_alt((grid, start, end): &InputPathfinding) -> Num {
     let res = pathfinding::directed::dijkstra::dijkstra(
         &start,
         #[inline(always)]
-        |Node(x, y, d)| {
-            let mut turns: SmallVec<[_; 3]> = smallvec![
-                (Node(*x, *y, d.turn_left()), 1000),
-                (Node(*x, *y, d.turn_right()), 1000),
-            ];
-            let forward = d.advance((*x, *y));
-            if grid.is_passable(forward.0, forward.1) {
-                turns.push((Node(forward.0, forward.1, *d), 1));
-            }
-            turns
-        },
+        |node| MazeCosts::REINDEER.successors(grid, node),
         #[inline(always)]
         |node| node.0 == end.0 && node.1 == end.1,
     )
@@ -292,16 +400,11 @@ fn one_astar((grid, start, end): &InputPathfinding) -> usize {
     let res = pathfinding::directed::astar::astar(
         &start,
         #[inline(always)]
-        |Node(x, y, d)| {
-            let mut turns: SmallVec<[_; 3]> = smallvec![
-                (Node(*x, *y, d.turn_left()), 1000),
-                (Node(*x, *y, d.turn_right()), 1000),
-            ];
-            let forward = d.advance((*x, *y));
-            if grid.is_passable(forward.0, forward.1) {
-                turns.push((Node(forward.0, forward.1, *d), 1));
-            }
-            turns
+        |node| {
+            MazeCosts::REINDEER
+                .successors(grid, node)
+                .into_iter()
+                .map(|(node, cost)| (node, cost as usize))
         },
         #[inline(always)]
         |node| (node.0.abs_diff(end.0) + node.1.abs_diff(end.1)) as usize,
@@ -319,23 +422,20 @@ fn two_astar((grid, start, end): &InputPathfinding) -> usize {
     let res = pathfinding::directed::astar::astar_bag(
         &start,
         #[inline(always)]
-        |Node(x, y, d)| {
-            let mut turns: SmallVec<[_; 3]> = smallvec![
-                (Node(*x, *y, d.turn_left()), 1000),
-                (Node(*x, *y, d.turn_right()), 1000),
-            ];
-            let forward = d.advance((*x, *y));
-            if grid.is_passable(forward.0, forward.1) {
-                turns.push((Node(forward.0, forward.1, *d), 1));
-            }
-            turns
+        |node| {
+            MazeCosts::REINDEER
+                .successors(grid, node)
+                .into_iter()
+                .map(|(node, cost)| (node, cost as usize))
         },
         #[inline(always)]
         |node| {
             node.0.abs_diff(end.0) as usize
                 + node.1.abs_diff(end.1) as usize
                 + if node.0 != end.0 && node.1 != end.1 {
-                    1000
+                    MazeCosts::REINDEER
+                        .turn_left
+                        .min(MazeCosts::REINDEER.turn_right) as usize
                 } else {
                     0
                 }
@@ -382,4 +482,11 @@ mod examples {
         let res = part2(include_str!("test2.txt"));
         assert_eq!(res, 64);
     }
+
+    #[test]
+    fn example_corridors() {
+        let graph = parse_corridors(include_str!("test2.txt"));
+        assert_eq!(one_corridors(&graph), 11048);
+        assert_eq!(two_corridors(&graph), 64);
+    }
 }
@@ -0,0 +1,99 @@
+use pathfinding::directed::yen::yen;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use super::{parse_with_costs, Coord, Facing, MazeCosts, Num};
+
+/// Facing of each of the four nodes of a tile, in the order `parse_with_costs` creates them.
+const NODE_FACINGS: [Facing; 4] = [Facing::North, Facing::East, Facing::South, Facing::West];
+
+/// A route through the maze and the states it passes, from the start to the first time it
+/// reaches the end tile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankedRoute {
+    pub cost: Num,
+    pub states: Vec<(Coord, Coord, Facing)>,
+}
+
+/// The `k` cheapest routes without repeated states, cheapest first.
+pub fn k_shortest(puzzle: &str, costs: &MazeCosts, k: usize) -> Vec<RankedRoute> {
+    let (maze, start, end, _, _) = parse_with_costs(puzzle, costs);
+    let mut tiles = Vec::new();
+    for (y, line) in puzzle.trim_ascii().lines().enumerate() {
+        for (x, ch) in line.bytes().enumerate() {
+            if ch != b'#' {
+                tiles.push((x as Coord, y as Coord));
+            }
+        }
+    }
+    let state = |node: NodeIndex| {
+        let (x, y) = tiles[node.index() / 4];
+        (x, y, NODE_FACINGS[node.index() % 4])
+    };
+
+    yen(
+        &start,
+        |&node| {
+            maze.edges(node)
+                .map(|edge| (edge.target(), *edge.weight()))
+                .collect::<Vec<_>>()
+        },
+        |node| end.contains(node),
+        k,
+    )
+    .into_iter()
+    .map(|(nodes, cost)| RankedRoute {
+        cost,
+        states: nodes.into_iter().map(state).collect(),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day16::part1;
+
+    #[test]
+    fn examples() {
+        for input in [include_str!("test.txt"), include_str!("test2.txt")] {
+            let routes = k_shortest(input, &MazeCosts::REINDEER, 5);
+            assert_eq!(routes.len(), 5);
+            assert_eq!(routes[0].cost, part1(input));
+            assert!(routes.windows(2).all(|pair| pair[0].cost <= pair[1].cost));
+            assert!(routes
+                .iter()
+                .all(|route| route.states[0] == routes[0].states[0]));
+        }
+    }
+
+    #[test]
+    fn second_best() {
+        let maze = "#######\n#....E#\n#.###.#\n#S....#\n#######";
+        let routes = k_shortest(maze, &MazeCosts::REINDEER, 3);
+        let costs: Vec<Num> = routes.iter().map(|route| route.cost).collect();
+        assert_eq!(costs, vec![1006, 2006, 3006]);
+        assert_eq!(routes[0].states.last(), Some(&(5, 1, Facing::North)));
+    }
+
+    #[test]
+    fn cost_variants() {
+        let maze = "#####\n#E.S#\n#####";
+        assert_eq!(k_shortest(maze, &MazeCosts::REINDEER, 1)[0].cost, 2002);
+        let cheap_u_turn = MazeCosts {
+            u_turn: 500,
+            ..MazeCosts::REINDEER
+        };
+        let route = &k_shortest(maze, &cheap_u_turn, 1)[0];
+        assert_eq!(route.cost, 502);
+        assert_eq!(route.states.len(), 4);
+
+        let lopsided = MazeCosts {
+            turn_left: 10,
+            turn_right: 1,
+            ..MazeCosts::default()
+        };
+        let maze = "#######\n#....E#\n#.###.#\n#S....#\n#######";
+        assert_eq!(k_shortest(maze, &lopsided, 1)[0].cost, 9);
+    }
+}
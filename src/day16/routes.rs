@@ -0,0 +1,389 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use super::{parse_alt, Coord, Facing, Grid2D, MazeCosts, Num};
+
+const FACINGS: [Facing; 4] = [Facing::North, Facing::South, Facing::West, Facing::East];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Forward,
+    TurnLeft,
+    TurnRight,
+    UTurn,
+}
+
+impl Action {
+    pub fn cost(self, costs: &MazeCosts) -> Num {
+        match self {
+            Action::Forward => costs.forward,
+            Action::TurnLeft => costs.turn_left,
+            Action::TurnRight => costs.turn_right,
+            Action::UTurn => costs.u_turn,
+        }
+    }
+}
+
+/// One action of a route and the state the reindeer performs it in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteStep {
+    pub tile: (Coord, Coord),
+    pub facing: Facing,
+    pub action: Action,
+}
+
+type State = usize;
+
+/// All cheapest routes through the maze, as the subgraph of states lying on any of them.
+pub struct OptimalRoutes {
+    grid: Grid2D,
+    costs: MazeCosts,
+    start: State,
+    end: (Coord, Coord),
+    cost: Num,
+    distances: Vec<Num>,
+    on_route: Vec<bool>,
+}
+
+impl OptimalRoutes {
+    fn state(&self, (x, y): (Coord, Coord), facing: Facing) -> State {
+        self.grid.to_index(x, y) * 4 + facing as usize
+    }
+
+    fn tile_and_facing(&self, state: State) -> ((Coord, Coord), Facing) {
+        (self.grid.to_point(state / 4), FACINGS[state % 4])
+    }
+
+    fn successors(&self, state: State) -> impl Iterator<Item = (Action, State)> + '_ {
+        let (tile, facing) = self.tile_and_facing(state);
+        let forward = facing.advance(tile);
+        [
+            (Action::TurnLeft, Some(self.state(tile, facing.turn_left()))),
+            (
+                Action::TurnRight,
+                Some(self.state(tile, facing.turn_right())),
+            ),
+            (
+                Action::UTurn,
+                self.costs
+                    .u_turn()
+                    .map(|_| self.state(tile, facing.reverse())),
+            ),
+            (
+                Action::Forward,
+                self.grid
+                    .is_passable(forward.0, forward.1)
+                    .then(|| self.state(forward, facing)),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(action, next)| Some((action, next?)))
+    }
+
+    fn predecessors(&self, state: State) -> impl Iterator<Item = (Action, State)> + '_ {
+        let (tile, facing) = self.tile_and_facing(state);
+        let behind = facing.reverse().advance(tile);
+        [
+            (
+                Action::TurnLeft,
+                Some(self.state(tile, facing.turn_right())),
+            ),
+            (
+                Action::TurnRight,
+                Some(self.state(tile, facing.turn_left())),
+            ),
+            (
+                Action::UTurn,
+                self.costs
+                    .u_turn()
+                    .map(|_| self.state(tile, facing.reverse())),
+            ),
+            (
+                Action::Forward,
+                self.grid
+                    .is_passable(behind.0, behind.1)
+                    .then(|| self.state(behind, facing)),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(action, previous)| Some((action, previous?)))
+    }
+
+    fn is_optimal_end(&self, state: State) -> bool {
+        self.tile_and_facing(state).0 == self.end && self.distances[state] == self.cost
+    }
+
+    /// Successors of `state` which continue some optimal route.
+    fn optimal_successors(&self, state: State) -> impl Iterator<Item = (Action, State)> + '_ {
+        self.successors(state).filter(move |&(action, next)| {
+            self.on_route[next]
+                && self.distances[state] + action.cost(&self.costs) == self.distances[next]
+        })
+    }
+
+    pub fn cost(&self) -> Num {
+        self.cost
+    }
+
+    /// Number of distinct optimal routes, which can grow exponentially with the maze size.
+    pub fn count(&self) -> BigUint {
+        let mut states: Vec<State> = (0..self.on_route.len())
+            .filter(|&state| self.on_route[state])
+            .collect();
+        states.sort_unstable_by_key(|&state| self.distances[state]);
+        let mut counts = vec![BigUint::zero(); self.on_route.len()];
+        counts[self.start] = BigUint::one();
+        let mut total = BigUint::zero();
+        for state in states {
+            if self.is_optimal_end(state) {
+                total += &counts[state];
+            }
+            let count = std::mem::take(&mut counts[state]);
+            for (_, next) in self.optimal_successors(state) {
+                counts[next] += &count;
+            }
+        }
+        total
+    }
+
+    /// Every optimal route, one after the other.
+    pub fn routes(&self) -> Routes<'_> {
+        Routes {
+            routes: self,
+            stack: vec![(self.start, self.optimal_successors(self.start).collect())],
+            steps: Vec::new(),
+        }
+    }
+
+    /// Tiles lying on at least one optimal route, in row-major order.
+    pub fn best_tiles(&self) -> Vec<(Coord, Coord)> {
+        let mut tiles: Vec<(Coord, Coord)> = (0..self.on_route.len())
+            .step_by(4)
+            .filter(|&state| self.on_route[state..state + 4].iter().any(|&on| on))
+            .map(|state| self.tile_and_facing(state).0)
+            .collect();
+        tiles.sort_unstable_by_key(|&(x, y)| (y, x));
+        tiles
+    }
+
+    /// The maze with every best tile drawn as `O`.
+    pub fn overlay(&self) -> String {
+        let mut overlay = self.grid.data.clone();
+        for (x, y) in self.best_tiles() {
+            overlay[self.grid.to_index(x, y)] = b'O';
+        }
+        String::from_utf8(overlay).unwrap()
+    }
+}
+
+/// The cheapest routes from the start facing east to the end, moving at the given costs, or
+/// `None` if the end cannot be reached.
+///
+/// Panics unless every cost is positive.
+pub fn optimal_routes(puzzle: &str, costs: &MazeCosts) -> Option<OptimalRoutes> {
+    costs.check();
+    let (grid, start, end) = parse_alt(puzzle.trim_end());
+    let states = grid.data.len() * 4;
+    let mut routes = OptimalRoutes {
+        grid,
+        costs: *costs,
+        start: 0,
+        end,
+        cost: Num::MAX,
+        distances: vec![Num::MAX; states],
+        on_route: vec![false; states],
+    };
+    routes.start = routes.state(start, Facing::East);
+
+    let mut queue = BinaryHeap::from([Reverse((0, routes.start))]);
+    routes.distances[routes.start] = 0;
+    while let Some(Reverse((distance, state))) = queue.pop() {
+        if distance > routes.distances[state] {
+            continue;
+        }
+        let successors: Vec<(Action, State)> = routes.successors(state).collect();
+        for (action, next) in successors {
+            let next_distance = distance + action.cost(costs);
+            if next_distance < routes.distances[next] {
+                routes.distances[next] = next_distance;
+                queue.push(Reverse((next_distance, next)));
+            }
+        }
+    }
+
+    routes.cost = FACINGS
+        .iter()
+        .map(|&facing| routes.distances[routes.state(end, facing)])
+        .min()
+        .unwrap();
+    if routes.cost == Num::MAX {
+        return None;
+    }
+    let mut stack: Vec<State> = FACINGS
+        .iter()
+        .map(|&facing| routes.state(end, facing))
+        .filter(|&state| routes.distances[state] == routes.cost)
+        .collect();
+    while let Some(state) = stack.pop() {
+        if std::mem::replace(&mut routes.on_route[state], true) {
+            continue;
+        }
+        let previous = routes.predecessors(state).filter(|&(action, previous)| {
+            let distance = routes.distances[previous];
+            distance != Num::MAX && distance + action.cost(costs) == routes.distances[state]
+        });
+        stack.extend(previous.map(|(_, previous)| previous).collect::<Vec<_>>());
+    }
+    Some(routes)
+}
+
+/// Depth-first walk over the optimal routes.
+pub struct Routes<'r> {
+    routes: &'r OptimalRoutes,
+    stack: Vec<(State, Vec<(Action, State)>)>,
+    steps: Vec<RouteStep>,
+}
+
+impl Iterator for Routes<'_> {
+    type Item = Vec<RouteStep>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (state, successors) = self.stack.last_mut()?;
+            let state = *state;
+            let Some((action, next)) = successors.pop() else {
+                self.stack.pop();
+                self.steps.pop();
+                continue;
+            };
+            let (tile, facing) = self.routes.tile_and_facing(state);
+            self.steps.push(RouteStep {
+                tile,
+                facing,
+                action,
+            });
+            self.stack
+                .push((next, self.routes.optimal_successors(next).collect()));
+            if self.routes.is_optimal_end(next) {
+                return Some(self.steps.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        for (input, cost, tiles) in [
+            (include_str!("test.txt"), 7036, 45),
+            (include_str!("test2.txt"), 11048, 64),
+        ] {
+            let routes = optimal_routes(input, &MazeCosts::REINDEER).unwrap();
+            assert_eq!(routes.cost(), cost);
+            assert_eq!(routes.best_tiles().len(), tiles);
+            assert_eq!(routes.overlay().matches('O').count(), tiles);
+
+            let all: Vec<Vec<RouteStep>> = routes.routes().collect();
+            assert_eq!(BigUint::from(all.len()), routes.count());
+            for route in &all {
+                let total: Num = route
+                    .iter()
+                    .map(|step| step.action.cost(&MazeCosts::REINDEER))
+                    .sum();
+                assert_eq!(total, cost);
+            }
+            let mut covered: Vec<(Coord, Coord)> =
+                all.iter().flatten().map(|step| step.tile).collect();
+            covered.push(routes.end);
+            covered.sort_unstable_by_key(|&(x, y)| (y, x));
+            covered.dedup();
+            assert_eq!(covered, routes.best_tiles());
+        }
+    }
+
+    #[test]
+    fn exponential_count() {
+        // Every ring can be passed above or below its pillar at the same cost.
+        let rings = 70;
+        let row =
+            |start: &str, ring: &str, end: &str| format!("#{start}{}{end}#\n", ring.repeat(rings));
+        let maze = [
+            row("#", "####", "#"),
+            row("#", "...#", "#"),
+            row("S", ".#..", "E"),
+            row("#", "...#", "#"),
+            row("#", "####", "#"),
+        ]
+        .concat();
+        let routes = optimal_routes(&maze, &MazeCosts::REINDEER).unwrap();
+        assert_eq!(routes.cost(), rings as Num * 4006 + 1);
+        assert_eq!(routes.count(), BigUint::one() << rings);
+    }
+
+    #[test]
+    fn cost_models() {
+        use crate::day16::ranked::k_shortest;
+
+        let maze = "#####\n#E.S#\n#####";
+        let cheap_u_turn = MazeCosts {
+            u_turn: 500,
+            ..MazeCosts::REINDEER
+        };
+        let routes = optimal_routes(maze, &cheap_u_turn).unwrap();
+        assert_eq!(routes.cost(), 502);
+        let actions: Vec<Action> = routes
+            .routes()
+            .next()
+            .unwrap()
+            .iter()
+            .map(|step| step.action)
+            .collect();
+        assert_eq!(
+            actions,
+            vec![Action::UTurn, Action::Forward, Action::Forward]
+        );
+
+        let lopsided = MazeCosts {
+            forward: 3,
+            turn_left: 7,
+            turn_right: 400,
+            u_turn: 2000,
+        };
+        for input in [include_str!("test.txt"), include_str!("test2.txt")] {
+            let routes = optimal_routes(input, &lopsided).unwrap();
+            assert_eq!(routes.cost(), k_shortest(input, &lopsided, 1)[0].cost);
+            for route in routes.routes().take(20) {
+                let total: Num = route.iter().map(|step| step.action.cost(&lopsided)).sum();
+                assert_eq!(total, routes.cost());
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_end() {
+        let maze = "#######\n#S.#.E#\n#######";
+        assert!(optimal_routes(maze, &MazeCosts::REINDEER).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "maze costs must be positive")]
+    fn free_turns() {
+        let free_turns = MazeCosts {
+            turn_left: 0,
+            ..MazeCosts::REINDEER
+        };
+        optimal_routes("#####\n#S.E#\n#####\n", &free_turns);
+    }
+
+    #[test]
+    #[should_panic(expected = "maze costs must be positive")]
+    fn new_rejects_free_moves() {
+        MazeCosts::new(0, 1000, 1000, 2000);
+    }
+}
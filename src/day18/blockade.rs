@@ -0,0 +1,316 @@
+use std::collections::VecDeque;
+
+use petgraph::unionfind::UnionFind;
+
+use super::{Coord, Point};
+
+/// The removal after which two parts of the grid no longer reach each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blockade {
+    /// Index of the removal in the list.
+    pub time: usize,
+    pub point: Point,
+    /// The 8-connected chain of removed cells which `point` completed, in row-major order.
+    /// Removed cells elsewhere, which take no part in the cut, are left out.
+    pub wall: Vec<Point>,
+}
+
+/// Cells removed one after the other from a grid whose free cells are 4-connected.
+pub struct Removals<'r> {
+    width: Coord,
+    height: Coord,
+    cells: &'r [Point],
+}
+
+impl<'r> Removals<'r> {
+    pub fn new(width: Coord, height: Coord, cells: &'r [Point]) -> Self {
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    fn index(&self, point: Point) -> usize {
+        point.1 as usize * self.width as usize + point.0 as usize
+    }
+
+    fn point(&self, index: usize) -> Point {
+        Point(
+            (index % self.width as usize) as Coord,
+            (index / self.width as usize) as Coord,
+        )
+    }
+
+    fn neighbours(&self, point: Point, diagonal: bool) -> impl Iterator<Item = Point> + '_ {
+        const OFFSETS: [(i16, i16); 8] = [
+            (0, -1),
+            (0, 1),
+            (-1, 0),
+            (1, 0),
+            (-1, -1),
+            (-1, 1),
+            (1, -1),
+            (1, 1),
+        ];
+        OFFSETS[..if diagonal { 8 } else { 4 }]
+            .iter()
+            .map(move |&(dx, dy)| {
+                Point(
+                    point.0.wrapping_add_signed(dx),
+                    point.1.wrapping_add_signed(dy),
+                )
+            })
+            .filter(|p| p.0 < self.width && p.1 < self.height)
+    }
+
+    /// The border cells in clockwise order, starting at the top left corner. On a space one cell
+    /// wide or tall, the cells between the two ends lie on both sides of it and are listed twice.
+    fn border(&self) -> Vec<Point> {
+        let (max_x, max_y) = (self.width - 1, self.height - 1);
+        let mut border: Vec<Point> = (0..max_x).map(|x| Point(x, 0)).collect();
+        border.extend((0..max_y).map(|y| Point(max_x, y)));
+        border.extend((1..=max_x).rev().map(|x| Point(x, max_y)));
+        border.extend((1..=max_y).rev().map(|y| Point(0, y)));
+        if border.is_empty() {
+            border.push(Point(0, 0));
+        }
+        border.dedup();
+        border
+    }
+
+    /// The removal at `time` with the 8-connected chain of removed cells it belongs to.
+    fn blockade(&self, time: usize, removed: &[bool]) -> Blockade {
+        let point = self.cells[time];
+        let mut in_chain = vec![false; removed.len()];
+        in_chain[self.index(point)] = true;
+        let mut wall = vec![point];
+        let mut queue = VecDeque::from([point]);
+        while let Some(p) = queue.pop_front() {
+            for next in self.neighbours(p, true) {
+                let i = self.index(next);
+                if removed[i] && !in_chain[i] {
+                    in_chain[i] = true;
+                    wall.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+        wall.sort_unstable_by_key(|p| (p.1, p.0));
+        Blockade { time, point, wall }
+    }
+
+    /// First removal separating the border cells `from` and `to`.
+    ///
+    /// They split the border into two arcs, and a path between them exists as long as no
+    /// 8-connected chain of removed cells links one arc to the other. Chains are tracked with
+    /// a union-find over removed cells as they come, and the wall is the chain which links the
+    /// arcs, without unrelated cells the union-find only joined through an arc.
+    pub fn first_cut_forward(&self, from: Point, to: Point) -> Option<Blockade> {
+        let border = self.border();
+        let position = |p| border.iter().position(|&b| b == p);
+        let (from_at, to_at) = (
+            position(from).expect("`from` lies on the border"),
+            position(to).expect("`to` lies on the border"),
+        );
+        let cells = self.width as usize * self.height as usize;
+        let (arc_a, arc_b) = (cells, cells + 1);
+        // The arcs each cell touches, both for the inner cells of a one cell wide space.
+        let mut arcs = vec![[false; 2]; cells];
+        for (at, &p) in border.iter().enumerate() {
+            let from_from = (at + border.len() - from_at) % border.len();
+            let from_to = (to_at + border.len() - from_at) % border.len();
+            if p != from && p != to {
+                arcs[self.index(p)][usize::from(from_from > from_to)] = true;
+            }
+        }
+
+        let mut walls: UnionFind<usize> = UnionFind::new(cells + 2);
+        let mut removed = vec![false; cells];
+        for (time, &p) in self.cells.iter().enumerate() {
+            let i = self.index(p);
+            if removed[i] {
+                continue;
+            }
+            removed[i] = true;
+            if p == from || p == to {
+                return Some(self.blockade(time, &removed));
+            }
+            for (arc, &touches) in [arc_a, arc_b].iter().zip(&arcs[i]) {
+                if touches {
+                    walls.union(i, *arc);
+                }
+            }
+            for next in self.neighbours(p, true) {
+                if removed[self.index(next)] {
+                    walls.union(i, self.index(next));
+                }
+            }
+            if walls.equiv(arc_a, arc_b) {
+                return Some(self.blockade(time, &removed));
+            }
+        }
+        None
+    }
+
+    /// First removal after which no cell of `from` reaches any cell of `to`, or `None` if they
+    /// stay connected or never were.
+    ///
+    /// Works backwards from the fully removed grid, putting cells back in reverse order and
+    /// joining free cells with a union-find until the two regions meet.
+    pub fn first_cut(&self, from: &[Point], to: &[Point]) -> Option<Blockade> {
+        let cells = self.width as usize * self.height as usize;
+        let (source, target) = (cells, cells + 1);
+        let mut first_removal = vec![usize::MAX; cells];
+        for (time, &p) in self.cells.iter().enumerate().rev() {
+            first_removal[self.index(p)] = time;
+        }
+        let mut removed: Vec<bool> = first_removal.iter().map(|&t| t != usize::MAX).collect();
+
+        let mut free: UnionFind<usize> = UnionFind::new(cells + 2);
+        for &p in from {
+            free.union(self.index(p), source);
+        }
+        for &p in to {
+            free.union(self.index(p), target);
+        }
+        for i in 0..cells {
+            if removed[i] {
+                continue;
+            }
+            for next in self.neighbours(self.point(i), false) {
+                if !removed[self.index(next)] {
+                    free.union(i, self.index(next));
+                }
+            }
+        }
+        let connected = |free: &UnionFind<usize>, removed: &[bool]| {
+            free.equiv(source, target)
+                && from.iter().any(|&p| !removed[self.index(p)])
+                && to.iter().any(|&p| !removed[self.index(p)])
+        };
+        if connected(&free, &removed) {
+            return None;
+        }
+
+        for (time, &p) in self.cells.iter().enumerate().rev() {
+            let i = self.index(p);
+            if first_removal[i] != time {
+                continue;
+            }
+            removed[i] = false;
+            for next in self.neighbours(p, false) {
+                if !removed[self.index(next)] {
+                    free.union(i, self.index(next));
+                }
+            }
+            if connected(&free, &removed) {
+                removed[i] = true;
+                return Some(self.blockade(time, &removed));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day18::parse;
+
+    #[test]
+    fn example() {
+        let points = parse(include_str!("test.txt"));
+        let removals = Removals::new(7, 7, &points);
+        let forward = removals
+            .first_cut_forward(Point(0, 0), Point(6, 6))
+            .unwrap();
+        let reverse = removals.first_cut(&[Point(0, 0)], &[Point(6, 6)]).unwrap();
+        assert_eq!(forward, reverse);
+        assert_eq!((forward.time, forward.point), (20, Point(6, 1)));
+        // The diagonal from (6, 1) down to (0, 6) with the cells attached to it; the debris
+        // around (2, 1) and (5, 4) is not part of the cut.
+        assert_eq!(
+            forward.wall,
+            vec![
+                Point(5, 1),
+                Point(6, 1),
+                Point(4, 2),
+                Point(3, 3),
+                Point(0, 4),
+                Point(1, 4),
+                Point(2, 4),
+                Point(1, 5),
+                Point(2, 5),
+                Point(0, 6),
+                Point(2, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn regions() {
+        // Walling in the middle of a 5x5 grid, the last removal closes the ring.
+        let ring = [
+            Point(1, 1),
+            Point(2, 1),
+            Point(3, 1),
+            Point(3, 2),
+            Point(3, 3),
+            Point(2, 3),
+            Point(1, 3),
+            Point(1, 2),
+        ];
+        let removals = Removals::new(5, 5, &ring);
+        let cut = removals.first_cut(&[Point(2, 2)], &[Point(0, 0)]).unwrap();
+        assert_eq!((cut.time, cut.point), (7, Point(1, 2)));
+        let mut ring_sorted = ring.to_vec();
+        ring_sorted.sort_unstable_by_key(|p| (p.1, p.0));
+        assert_eq!(cut.wall, ring_sorted);
+
+        let cut = removals.first_cut(&[Point(0, 0), Point(4, 0)], &[Point(2, 2)]);
+        assert_eq!(cut.map(|cut| cut.time), Some(7));
+        assert_eq!(removals.first_cut(&[Point(0, 0)], &[Point(4, 4)]), None);
+        assert_eq!(removals.first_cut_forward(Point(0, 0), Point(4, 4)), None);
+    }
+
+    #[test]
+    fn non_square_border() {
+        // A 6x2 strip, cut between its ends by a vertical pair.
+        let removals = [Point(3, 0), Point(0, 1), Point(3, 1)];
+        let removals = Removals::new(6, 2, &removals);
+        let forward = removals
+            .first_cut_forward(Point(0, 0), Point(5, 1))
+            .unwrap();
+        assert_eq!(forward.point, Point(3, 1));
+        assert_eq!(forward.wall, vec![Point(3, 0), Point(3, 1)]);
+        assert_eq!(
+            removals.first_cut(&[Point(0, 0)], &[Point(5, 1)]),
+            Some(forward)
+        );
+    }
+
+    #[test]
+    fn one_cell_wide() {
+        for (width, height) in [(1, 3), (3, 1), (1, 5), (5, 1)] {
+            let last = Point(width - 1, height - 1);
+            let cells: Vec<Point> = (0..width)
+                .flat_map(|x| (0..height).map(move |y| Point(x, y)))
+                .collect();
+            for &first in &cells {
+                for &second in &cells {
+                    let order = [first, second];
+                    let removals = Removals::new(width, height, &order);
+                    let forward = removals.first_cut_forward(Point(0, 0), last);
+                    assert_eq!(forward, removals.first_cut(&[Point(0, 0)], &[last]));
+                    assert_eq!(forward.map(|cut| cut.time), Some(0));
+                }
+            }
+        }
+        let removals = Removals::new(1, 3, &[Point(0, 1)]);
+        let cut = removals
+            .first_cut_forward(Point(0, 0), Point(0, 2))
+            .unwrap();
+        assert_eq!(cut.wall, vec![Point(0, 1)]);
+    }
+}
@@ -0,0 +1,192 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use super::{MemorySpace, Point};
+
+const UNREACHABLE: u32 = u32::MAX;
+
+/// Shortest path from the top left to the bottom right corner, kept up to date while bytes
+/// fall one after the other.
+///
+/// Each fallen byte only invalidates the cells whose every shortest path ran through it. Those
+/// are found by walking down the old distance layers from the byte, and then get their
+/// distances back from the untouched cells around them.
+pub struct FallingBytes<'p> {
+    points: &'p [Point],
+    fallen: usize,
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    distances: Vec<u32>,
+}
+
+impl<'p> FallingBytes<'p> {
+    pub fn new(points: &'p [Point], space: MemorySpace) -> Self {
+        let (width, height) = (space.width as usize, space.height as usize);
+        let mut bytes = Self {
+            points,
+            fallen: 0,
+            width,
+            height,
+            blocked: vec![false; width * height],
+            distances: vec![UNREACHABLE; width * height],
+        };
+        bytes.distances[0] = 0;
+        let mut queue = VecDeque::from([0]);
+        while let Some(cell) = queue.pop_front() {
+            let distance = bytes.distances[cell] + 1;
+            let neighbours: Vec<usize> = bytes.neighbours(cell).collect();
+            for next in neighbours {
+                if bytes.distances[next] == UNREACHABLE {
+                    bytes.distances[next] = distance;
+                    queue.push_back(next);
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Number of bytes fallen so far.
+    pub fn fallen(&self) -> usize {
+        self.fallen
+    }
+
+    /// Length of the shortest path across right now, if there still is one.
+    pub fn shortest_path(&self) -> Option<u32> {
+        let exit = self.distances[self.width * self.height - 1];
+        (exit != UNREACHABLE).then_some(exit)
+    }
+
+    /// Lets up to `n` more bytes fall and returns the new shortest path length.
+    pub fn advance(&mut self, n: usize) -> Option<u32> {
+        let until = (self.fallen + n).min(self.points.len());
+        for i in self.fallen..until {
+            let Point(x, y) = self.points[i];
+            self.drop_byte(y as usize * self.width + x as usize);
+        }
+        self.fallen = until;
+        self.shortest_path()
+    }
+
+    fn neighbours(&self, cell: usize) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = (cell % self.width, cell / self.width);
+        [
+            (y > 0).then(|| cell - self.width),
+            (y + 1 < self.height).then(|| cell + self.width),
+            (x > 0).then(|| cell - 1),
+            (x + 1 < self.width).then(|| cell + 1),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|&next| !self.blocked[next])
+    }
+
+    fn drop_byte(&mut self, cell: usize) {
+        let was_reachable = self.distances[cell] != UNREACHABLE;
+        self.blocked[cell] = true;
+        // Paths never come back once the exit is cut off, so distances are left stale from
+        // then on.
+        if !was_reachable || self.shortest_path().is_none() {
+            return;
+        }
+        if cell == 0 {
+            self.distances.fill(UNREACHABLE);
+            return;
+        }
+
+        // Cells lose their distance layer by layer when none of their neighbours one step
+        // closer to the start is left.
+        let mut distance = self.distances[cell];
+        self.distances[cell] = UNREACHABLE;
+        let mut layer = vec![cell];
+        let mut orphans = Vec::new();
+        while !layer.is_empty() {
+            let mut next_layer = Vec::new();
+            for &orphan in &layer {
+                let candidates: Vec<usize> = self.neighbours(orphan).collect();
+                for next in candidates {
+                    if self.distances[next] == distance + 1
+                        && !self
+                            .neighbours(next)
+                            .any(|parent| self.distances[parent] == distance)
+                    {
+                        self.distances[next] = UNREACHABLE;
+                        next_layer.push(next);
+                    }
+                }
+            }
+            orphans.extend(next_layer.iter().copied());
+            layer = next_layer;
+            distance += 1;
+        }
+
+        let mut queue = BinaryHeap::new();
+        for &orphan in &orphans {
+            let best = self
+                .neighbours(orphan)
+                .map(|parent| self.distances[parent])
+                .min()
+                .unwrap_or(UNREACHABLE);
+            if best != UNREACHABLE {
+                self.distances[orphan] = best + 1;
+                queue.push(Reverse((best + 1, orphan)));
+            }
+        }
+        while let Some(Reverse((distance, orphan))) = queue.pop() {
+            if distance > self.distances[orphan] {
+                continue;
+            }
+            let closer: Vec<usize> = self
+                .neighbours(orphan)
+                .filter(|&next| self.distances[next] > distance + 1)
+                .collect();
+            for next in closer {
+                self.distances[next] = distance + 1;
+                queue.push(Reverse((distance + 1, next)));
+            }
+        }
+    }
+}
+
+/// Shortest path length after each number of fallen bytes, from none up to all of them.
+pub fn timeline(points: &[Point], space: MemorySpace) -> Vec<Option<u32>> {
+    let mut bytes = FallingBytes::new(points, space);
+    let mut timeline = vec![bytes.shortest_path()];
+    timeline.extend((0..points.len()).map(|_| bytes.advance(1)));
+    timeline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day18::{one_inner, parse};
+
+    #[test]
+    fn example_timeline() {
+        let points = parse(include_str!("test.txt"));
+        let space = MemorySpace::EXAMPLE;
+        let timeline = timeline(&points, space);
+        assert_eq!(timeline.len(), points.len() + 1);
+        assert_eq!(timeline[0], Some(12));
+        assert_eq!(timeline[space.bytes], Some(22));
+        let blocked = timeline.iter().position(Option::is_none).unwrap();
+        assert_eq!(points[blocked - 1], Point(6, 1));
+        for (fallen, length) in timeline.iter().enumerate().take(blocked) {
+            assert_eq!(
+                length.map(|length| length as usize),
+                Some(one_inner(&points[..fallen], space))
+            );
+        }
+    }
+
+    #[test]
+    fn advance_in_steps() {
+        let points = parse(include_str!("test.txt"));
+        let mut bytes = FallingBytes::new(&points, MemorySpace::EXAMPLE);
+        assert_eq!(bytes.advance(5), Some(12));
+        assert_eq!(bytes.advance(7), Some(22));
+        assert_eq!(bytes.fallen(), 12);
+        assert_eq!(bytes.advance(100), None);
+        assert_eq!(bytes.fallen(), points.len());
+    }
+}
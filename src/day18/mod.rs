@@ -3,18 +3,21 @@ use pathfinding::{
     directed::{bfs::bfs, dijkstra::dijkstra},
     prelude::astar,
 };
-use petgraph::{csr::IndexType, unionfind::UnionFind};
+use petgraph::unionfind::UnionFind;
 use rustc_hash::{FxHashMap, FxHashSet};
 use smallvec::SmallVec;
-use std::{cmp::Ordering, hash::Hash};
+use std::hash::Hash;
 
 use crate::util::parse_initial_digits;
 
+pub mod blockade;
+pub mod falling;
+
 type Input = Vec<Point>;
 
-type Coord = u16;
+pub type Coord = u16;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct Point(Coord, Coord);
+pub struct Point(pub Coord, pub Coord);
 
 impl Hash for Point {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
@@ -22,6 +25,74 @@ impl Hash for Point {
     }
 }
 
+/// Dimensions of the memory space, either fixed at compile time or chosen at runtime.
+trait Bounds: Copy {
+    fn width(self) -> Coord;
+    fn height(self) -> Coord;
+
+    #[inline(always)]
+    fn max_x(self) -> Coord {
+        self.width() - 1
+    }
+
+    #[inline(always)]
+    fn max_y(self) -> Coord {
+        self.height() - 1
+    }
+}
+
+/// A square memory space whose size is known at compile time.
+#[derive(Debug, Clone, Copy)]
+struct Square<const WIDTH: Coord>;
+
+impl<const WIDTH: Coord> Bounds for Square<WIDTH> {
+    #[inline(always)]
+    fn width(self) -> Coord {
+        WIDTH
+    }
+
+    #[inline(always)]
+    fn height(self) -> Coord {
+        WIDTH
+    }
+}
+
+/// Size of the memory space and how many bytes have fallen for part 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemorySpace {
+    pub width: Coord,
+    pub height: Coord,
+    pub bytes: usize,
+}
+
+impl MemorySpace {
+    pub const PUZZLE: MemorySpace = MemorySpace {
+        width: 71,
+        height: 71,
+        bytes: 1024,
+    };
+
+    pub const EXAMPLE: MemorySpace = MemorySpace {
+        width: 7,
+        height: 7,
+        bytes: 12,
+    };
+}
+
+impl Bounds for MemorySpace {
+    #[inline(always)]
+    fn width(self) -> Coord {
+        self.width
+    }
+
+    #[inline(always)]
+    fn height(self) -> Coord {
+        self.height
+    }
+}
+
+type PuzzleSpace = Square<71>;
+
 #[inline]
 #[aoc_generator(day18)]
 fn parse(input: &str) -> Input {
@@ -43,22 +114,27 @@ fn parse(input: &str) -> Input {
 #[inline]
 #[aoc(day18, part1)]
 fn one(points: &[Point]) -> usize {
-    one_inner::<70>(&points[..1024])
+    one_inner(&points[..MemorySpace::PUZZLE.bytes], PuzzleSpace {})
 }
 
 #[inline]
-fn one_inner<const SIZE: Coord>(points: &[Point]) -> usize {
-    find_path_across::<SIZE>(points).unwrap().len() - 1
+fn one_inner(points: &[Point], bounds: impl Bounds) -> usize {
+    find_path_across(points, bounds).unwrap().len() - 1
 }
 
 #[inline]
 #[aoc(day18, part1, astar)]
 fn one_astar(points: &[Point]) -> Coord {
-    find_path_across_astar::<70>(&points[..1024]).unwrap().1
+    one_inner_astar(&points[..MemorySpace::PUZZLE.bytes], PuzzleSpace {})
+}
+
+#[inline]
+fn one_inner_astar(points: &[Point], bounds: impl Bounds) -> Coord {
+    find_path_across_astar(points, bounds).unwrap().1
 }
 
 #[inline]
-fn find_path_across<const SIZE: Coord>(points: &[Point]) -> Option<Vec<Point>> {
+fn find_path_across(points: &[Point], bounds: impl Bounds) -> Option<Vec<Point>> {
     let obstacles = FxHashSet::from_iter(points);
     let start = Point(0, 0);
     bfs(
@@ -66,13 +142,13 @@ fn find_path_across<const SIZE: Coord>(points: &[Point]) -> Option<Vec<Point>> {
         #[inline]
         |node: &Point| {
             let mut neigh = SmallVec::<[_; 4]>::new();
-            if node.1 < SIZE {
+            if node.1 < bounds.max_y() {
                 let south = Point(node.0, node.1 + 1);
                 if !obstacles.contains(&south) {
                     neigh.push(south);
                 }
             }
-            if node.0 < SIZE {
+            if node.0 < bounds.max_x() {
                 let east = Point(node.0 + 1, node.1);
                 if !obstacles.contains(&east) {
                     neigh.push(east);
@@ -94,12 +170,12 @@ fn find_path_across<const SIZE: Coord>(points: &[Point]) -> Option<Vec<Point>> {
             neigh
         },
         #[inline(always)]
-        |node| node.0 == SIZE && node.1 == SIZE,
+        |node| node.0 == bounds.max_x() && node.1 == bounds.max_y(),
     )
 }
 
 #[inline]
-fn find_path_across_astar<const SIZE: Coord>(points: &[Point]) -> Option<(Vec<Point>, Coord)> {
+fn find_path_across_astar(points: &[Point], bounds: impl Bounds) -> Option<(Vec<Point>, Coord)> {
     let obstacles = FxHashSet::from_iter(points);
     let start = Point(0, 0);
     astar(
@@ -107,13 +183,13 @@ fn find_path_across_astar<const SIZE: Coord>(points: &[Point]) -> Option<(Vec<Po
         #[inline]
         |node: &Point| {
             let mut neigh = SmallVec::<[_; 4]>::new();
-            if node.1 < SIZE {
+            if node.1 < bounds.max_y() {
                 let south = Point(node.0, node.1 + 1);
                 if !obstacles.contains(&south) {
                     neigh.push((south, 1));
                 }
             }
-            if node.0 < SIZE {
+            if node.0 < bounds.max_x() {
                 let east = Point(node.0 + 1, node.1);
                 if !obstacles.contains(&east) {
                     neigh.push((east, 1));
@@ -135,16 +211,17 @@ fn find_path_across_astar<const SIZE: Coord>(points: &[Point]) -> Option<(Vec<Po
             neigh
         },
         #[inline]
-        |node| 2 * SIZE - node.0 - node.1,
+        |node| bounds.max_x() - node.0 + bounds.max_y() - node.1,
         #[inline(always)]
-        |node| node.0 == SIZE && node.1 == SIZE,
+        |node| node.0 == bounds.max_x() && node.1 == bounds.max_y(),
     )
 }
 
 #[inline]
-fn find_path_across_map<const SIZE: Coord>(
+fn find_path_across_map(
     obstacles: &FxHashMap<&Point, usize>,
     time: usize,
+    bounds: impl Bounds,
 ) -> Option<Vec<Point>> {
     let start = Point(0, 0);
     bfs(
@@ -152,13 +229,13 @@ fn find_path_across_map<const SIZE: Coord>(
         #[inline]
         |node: &Point| {
             let mut neigh = SmallVec::<[_; 4]>::new();
-            if node.1 < SIZE {
+            if node.1 < bounds.max_y() {
                 let south = Point(node.0, node.1 + 1);
                 if !obstacles.get(&south).is_some_and(|t| *t < time) {
                     neigh.push(south);
                 }
             }
-            if node.0 < SIZE {
+            if node.0 < bounds.max_x() {
                 let east = Point(node.0 + 1, node.1);
                 if !obstacles.get(&east).is_some_and(|t| *t < time) {
                     neigh.push(east);
@@ -180,19 +257,19 @@ fn find_path_across_map<const SIZE: Coord>(
             neigh
         },
         #[inline(always)]
-        |node| node.0 == SIZE && node.1 == SIZE,
+        |node| node.0 == bounds.max_x() && node.1 == bounds.max_y(),
     )
 }
 
 #[inline]
 #[aoc(day18, part2, blockade_dijkstra)]
 fn two(points: &[Point]) -> String {
-    let solution = two_inner::<70>(points);
+    let solution = two_inner(points, PuzzleSpace {});
     format!("{},{}", solution.0, solution.1)
 }
 
 #[inline]
-fn two_inner<const SIZE: Coord>(points: &[Point]) -> Point {
+fn two_inner(points: &[Point], bounds: impl Bounds) -> Point {
     let drop_time = FxHashMap::from_iter(points.iter().enumerate().map(|(i, p)| (p, i)));
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -237,7 +314,7 @@ fn two_inner<const SIZE: Coord>(points: &[Point]) -> Point {
                 Node::Waiting(time) => {
                     if *time < points.len() {
                         let new_point = points[*time];
-                        if new_point.0 == 0 || new_point.1 == SIZE {
+                        if new_point.0 == 0 || new_point.1 == bounds.max_y() {
                             neigh.push((Node::Spacetime(*time, new_point), 0));
                         }
                         neigh.push((Node::Waiting(time + 1), 1));
@@ -247,7 +324,7 @@ fn two_inner<const SIZE: Coord>(points: &[Point]) -> Point {
             neigh
         },
         |node| match node {
-            Node::Spacetime(_, point) => point.0 == SIZE || point.1 == 0,
+            Node::Spacetime(_, point) => point.0 == bounds.max_x() || point.1 == 0,
             Node::Waiting(_) => false,
         },
     )
@@ -261,12 +338,12 @@ fn two_inner<const SIZE: Coord>(points: &[Point]) -> Point {
 #[inline]
 #[aoc(day18, part2, blockade_astar)]
 fn two_astar(points: &[Point]) -> String {
-    let solution = two_inner_astar::<70>(points);
+    let solution = two_inner_astar(points, PuzzleSpace {});
     format!("{},{}", solution.0, solution.1)
 }
 
 #[inline]
-fn two_inner_astar<const SIZE: Coord>(points: &[Point]) -> Point {
+fn two_inner_astar(points: &[Point], bounds: impl Bounds) -> Point {
     let drop_time = FxHashMap::from_iter(points.iter().enumerate().map(|(i, p)| (p, i)));
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -312,7 +389,7 @@ fn two_inner_astar<const SIZE: Coord>(points: &[Point]) -> Point {
                 Node::Waiting(time) => {
                     if *time < points.len() {
                         let new_point = points[*time];
-                        if new_point.0 == 0 || new_point.1 == SIZE {
+                        if new_point.0 == 0 || new_point.1 == bounds.max_y() {
                             neigh.push((Node::Spacetime(*time, new_point), 1));
                         }
                         neigh.push((Node::Waiting(time + 1), 1 << 16));
@@ -324,13 +401,13 @@ fn two_inner_astar<const SIZE: Coord>(points: &[Point]) -> Point {
         #[inline]
         |node| {
             match node {
-                Node::Spacetime(_, point) => (SIZE - point.0).max(point.1) as usize,
+                Node::Spacetime(_, point) => (bounds.max_x() - point.0).max(point.1) as usize,
                 Node::Waiting(_) => 2, // = min length of a diagonal wall
             }
         },
         #[inline]
         |node| match node {
-            Node::Spacetime(_, point) => point.0 == SIZE || point.1 == 0,
+            Node::Spacetime(_, point) => point.0 == bounds.max_x() || point.1 == 0,
             Node::Waiting(_) => false,
         },
     )
@@ -344,208 +421,222 @@ fn two_inner_astar<const SIZE: Coord>(points: &[Point]) -> Point {
 #[inline]
 #[aoc(day18, part2, binary_search)]
 fn two_binary_search(points: &[Point]) -> String {
-    let indexed = points.iter().enumerate().collect::<Vec<_>>();
-    let p = indexed.partition_point(|(i, _)| find_path_across::<70>(&points[..*i]).is_some());
-    let solution = points[p - 1];
+    let solution = two_binary_search_inner(points, PuzzleSpace {});
     format!("{},{}", solution.0, solution.1)
 }
 
+#[inline]
+fn two_binary_search_inner(points: &[Point], bounds: impl Bounds) -> Point {
+    let indexed = points.iter().enumerate().collect::<Vec<_>>();
+    let p = indexed.partition_point(|(i, _)| find_path_across(&points[..*i], bounds).is_some());
+    points[p - 1]
+}
+
 #[inline]
 #[aoc(day18, part2, binary_search_map)]
 fn two_binary_search_map(points: &[Point]) -> String {
+    let solution = two_binary_search_map_inner(points, MemorySpace::PUZZLE.bytes, PuzzleSpace {});
+    format!("{},{}", solution.0, solution.1)
+}
+
+/// Binary search over drop times, knowing from part 1 that the first `passable` bytes leave
+/// a way across.
+#[inline]
+fn two_binary_search_map_inner(points: &[Point], passable: usize, bounds: impl Bounds) -> Point {
     let drop_time = FxHashMap::from_iter(points.iter().enumerate().map(|(i, p)| (p, i)));
 
-    let mut base = 1024; // From part 1
+    let mut base = passable;
     let mut size = points.len() - base;
 
     while size > 1 {
         let half = size / 2;
         let mid = base + half;
 
-        let can_cross = find_path_across_map::<70>(&drop_time, mid).is_some();
+        let can_cross = find_path_across_map(&drop_time, mid, bounds).is_some();
         base = if can_cross { mid } else { base };
 
         size -= half;
     }
 
-    let solution = points[base];
-    format!("{},{}", solution.0, solution.1)
+    points[base]
 }
 
 #[inline]
 #[aoc(day18, part2, binary_search_astar)]
 fn two_binary_search_astar(points: &[Point]) -> String {
-    let indexed = points.iter().enumerate().collect::<Vec<_>>();
-    let p = indexed.partition_point(|(i, _)| find_path_across_astar::<70>(&points[..*i]).is_some());
-    let solution = points[p - 1];
+    let solution = two_binary_search_astar_inner(points, PuzzleSpace {});
     format!("{},{}", solution.0, solution.1)
 }
 
+#[inline]
+fn two_binary_search_astar_inner(points: &[Point], bounds: impl Bounds) -> Point {
+    let indexed = points.iter().enumerate().collect::<Vec<_>>();
+    let p =
+        indexed.partition_point(|(i, _)| find_path_across_astar(&points[..*i], bounds).is_some());
+    points[p - 1]
+}
+
 #[aoc(day18, part2, union_find)]
 fn two_union_find(points: &[Point]) -> String {
-    #[cfg(test)]
-    const SIZE: Coord = 6;
-    #[cfg(not(test))]
-    const SIZE: Coord = 70;
-
-    #[derive(Debug, Clone, Copy, Hash, Default, PartialEq, Eq)]
-    enum Node {
-        #[default]
-        LowerLeft,
-        Tile(Point),
-        UpperRight,
-    }
-
-    impl Node {
-        const MAX: usize = ((SIZE + 1) * (SIZE + 1)) as usize - 1;
+    let solution = two_union_find_inner(points, PuzzleSpace {});
+    format!("{},{}", solution.0, solution.1)
+}
 
-        #[inline]
-        fn neighbors(&self) -> SmallVec<[Self; 8]> {
-            match self {
-                Node::Tile(point) => {
-                    let mut neigh = SmallVec::new();
-                    for (dx, dy) in [
-                        (-1, -1),
-                        (-1, 0),
-                        (-1, 1),
-                        (0, 1),
-                        (1, 1),
-                        (1, 0),
-                        (1, -1),
-                        (0, -1),
-                    ] {
-                        let p = Point(
-                            point.0.wrapping_add_signed(dx),
-                            point.1.wrapping_add_signed(dy),
-                        );
-                        if (p.0 > 0 || p.1 > 0)
-                            && (p.0 < SIZE || p.1 < SIZE)
-                            && (p.0 <= SIZE && p.1 <= SIZE)
-                        {
-                            neigh.push(Node::Tile(p))
-                        }
-                    }
-                    if point.0 == 0 || point.1 == SIZE {
-                        neigh.push(Node::LowerLeft);
-                    } else if point.0 == SIZE || point.1 == 0 {
-                        neigh.push(Node::UpperRight);
-                    }
-                    neigh
-                }
-                _ => SmallVec::new(),
+/// Links fallen bytes, 8-connected, to each other and to the two halves of the border. The
+/// byte joining the lower left border to the upper right one cuts every path across.
+fn two_union_find_inner(points: &[Point], bounds: impl Bounds) -> Point {
+    const LOWER_LEFT: usize = 0;
+    let (width, height) = (bounds.width() as usize, bounds.height() as usize);
+    let upper_right = width * height + 1;
+    let index = |point: Point| 1 + point.0 as usize + point.1 as usize * width;
+
+    let mut blockage: UnionFind<usize> = UnionFind::new(upper_right + 1);
+    let mut has_dropped = vec![false; upper_right + 1];
+    has_dropped[LOWER_LEFT] = true;
+    has_dropped[upper_right] = true;
+    for &new_p in points {
+        let node = index(new_p);
+        for (dx, dy) in [
+            (-1, -1),
+            (-1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+            (1, 0),
+            (1, -1),
+            (0, -1),
+        ] {
+            let p = Point(
+                new_p.0.wrapping_add_signed(dx),
+                new_p.1.wrapping_add_signed(dy),
+            );
+            if p.0 <= bounds.max_x() && p.1 <= bounds.max_y() && has_dropped[index(p)] {
+                blockage.union(node, index(p));
             }
         }
-    }
-
-    impl PartialOrd for Node {
-        #[inline]
-        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-            Some(self.cmp(other))
+        if new_p.0 == 0 || new_p.1 == bounds.max_y() {
+            blockage.union(node, LOWER_LEFT);
+        } else if new_p.0 == bounds.max_x() || new_p.1 == 0 {
+            blockage.union(node, upper_right);
         }
-    }
-
-    impl Ord for Node {
-        #[inline]
-        fn cmp(&self, other: &Self) -> Ordering {
-            match (self, other) {
-                (Node::LowerLeft, Node::LowerLeft) => Ordering::Equal,
-                (Node::LowerLeft, _) => Ordering::Less,
-                (Node::Tile(_), Node::LowerLeft) => Ordering::Greater,
-                (Node::Tile(point), Node::Tile(other)) => {
-                    (point.1, point.0).cmp(&(other.1, other.0))
-                }
-                (Node::Tile(_), Node::UpperRight) => Ordering::Less,
-                (Node::UpperRight, Node::UpperRight) => Ordering::Equal,
-                (Node::UpperRight, _) => Ordering::Greater,
-            }
+        if blockage.equiv(LOWER_LEFT, upper_right) {
+            return new_p;
         }
+        has_dropped[node] = true;
     }
+    panic!("No solution found")
+}
 
-    unsafe impl IndexType for Node {
-        #[inline]
-        fn new(x: usize) -> Self {
-            match x {
-                0 => Node::LowerLeft,
-                Self::MAX => Node::UpperRight,
-                x @ _ => {
-                    let p = Point(x as Coord % (SIZE + 1), x as Coord / (SIZE + 1));
-                    debug_assert!(p.0 > 0 || p.1 > 0, "Top-left corner is reserved");
-                    debug_assert!(p.0 < SIZE || p.1 < SIZE, "Bottom-right corner is reserved");
-                    debug_assert!(
-                        p.0 <= SIZE && p.1 <= SIZE,
-                        "Coordinates {p:?} should be within field"
-                    );
-                    Node::Tile(p)
-                }
-            }
-        }
+#[aoc(day18, part2, reverse_union_find)]
+fn two_reverse_union_find(points: &[Point]) -> String {
+    let space = MemorySpace::PUZZLE;
+    let exit = Point(space.width - 1, space.height - 1);
+    let solution = blockade::Removals::new(space.width, space.height, points)
+        .first_cut(&[Point(0, 0)], &[exit])
+        .expect("No solution found")
+        .point;
+    format!("{},{}", solution.0, solution.1)
+}
 
-        #[inline]
-        fn index(&self) -> usize {
-            match self {
-                Node::LowerLeft => 0,
-                Node::Tile(point) => (point.0 + point.1 * (SIZE + 1)) as usize,
-                Node::UpperRight => Self::MAX,
-            }
-        }
+#[aoc(day18, part2, falling_bytes)]
+fn two_falling_bytes(points: &[Point]) -> String {
+    let mut bytes = falling::FallingBytes::new(points, MemorySpace::PUZZLE);
+    let blocked = (0..points.len())
+        .find(|_| bytes.advance(1).is_none())
+        .expect("No solution found");
+    let solution = points[blocked];
+    format!("{},{}", solution.0, solution.1)
+}
 
-        #[inline]
-        fn max() -> Self {
-            Self::UpperRight
-        }
+/// Part 1 in a memory space of any size, using the compiled-in size where it matches.
+pub fn part1_in(puzzle: &str, space: MemorySpace) -> usize {
+    let points = parse(puzzle);
+    let points = &points[..space.bytes.min(points.len())];
+    if (space.width, space.height) == (71, 71) {
+        one_inner(points, PuzzleSpace {})
+    } else {
+        one_inner(points, space)
     }
+}
 
-    let mut blockage: UnionFind<Node> = UnionFind::new(Node::MAX + 1);
-    let mut has_dropped = vec![false; Node::MAX + 1];
-    has_dropped[Node::LowerLeft.index()] = true;
-    has_dropped[Node::UpperRight.index()] = true;
-    for new_p in points {
-        let node = Node::Tile(*new_p);
-        for neighbor in node.neighbors() {
-            if has_dropped[neighbor.index()] {
-                blockage.union(node, neighbor);
-            }
-        }
-        if blockage.equiv(Node::LowerLeft, Node::UpperRight) {
-            return format!("{},{}", new_p.0, new_p.1);
-        }
-        has_dropped[node.index()] = true;
-    }
-    panic!("No solution found")
+/// Part 2 in a memory space of any size, using the compiled-in size where it matches.
+pub fn part2_in(puzzle: &str, space: MemorySpace) -> String {
+    let points = parse(puzzle);
+    let solution = if (space.width, space.height) == (71, 71) {
+        two_union_find_inner(&points, PuzzleSpace {})
+    } else {
+        two_union_find_inner(&points, space)
+    };
+    format!("{},{}", solution.0, solution.1)
 }
 
 pub fn part1(puzzle: &str) -> usize {
-    one(&parse(puzzle))
+    part1_in(puzzle, MemorySpace::PUZZLE)
 }
 
 pub fn part2(puzzle: &str) -> String {
-    two_union_find(&parse(puzzle))
+    part2_in(puzzle, MemorySpace::PUZZLE)
 }
 
 #[cfg(test)]
 mod examples {
     use super::*;
 
+    const SPACE: MemorySpace = MemorySpace::EXAMPLE;
+
     #[test]
     fn example1() {
-        let res = one_inner::<6>(&parse(include_str!("test.txt"))[..12]);
-        assert_eq!(res, 22);
+        let points = parse(include_str!("test.txt"));
+        assert_eq!(one_inner(&points[..SPACE.bytes], SPACE), 22);
+        assert_eq!(one_inner(&points[..SPACE.bytes], Square::<7>), 22);
+        assert_eq!(one_inner_astar(&points[..SPACE.bytes], SPACE), 22);
+        assert_eq!(part1_in(include_str!("test.txt"), SPACE), 22);
     }
 
     #[test]
     fn example2() {
-        let res = two_inner::<6>(&parse(include_str!("test.txt")));
+        let res = two_inner(&parse(include_str!("test.txt")), SPACE);
         assert_eq!(res, Point(6, 1));
     }
 
     #[test]
     fn example2_astar() {
-        let res = two_inner_astar::<6>(&parse(include_str!("test.txt")));
+        let res = two_inner_astar(&parse(include_str!("test.txt")), SPACE);
         assert_eq!(res, Point(6, 1));
     }
+
+    #[test]
+    fn example2_binary_search() {
+        let points = parse(include_str!("test.txt"));
+        assert_eq!(two_binary_search_inner(&points, SPACE), Point(6, 1));
+        assert_eq!(two_binary_search_astar_inner(&points, SPACE), Point(6, 1));
+        assert_eq!(
+            two_binary_search_map_inner(&points, SPACE.bytes, SPACE),
+            Point(6, 1)
+        );
+    }
+
     #[test]
     fn example2_uf() {
-        let res = two_union_find(&parse(include_str!("test.txt")));
+        let res = part2_in(include_str!("test.txt"), SPACE);
         assert_eq!(res, "6,1");
     }
+
+    #[test]
+    fn non_square() {
+        // A 5x3 space cut by a vertical wall at x = 2, completed by its third byte.
+        let puzzle = "2,0\n2,2\n0,1\n2,1\n";
+        let space = MemorySpace {
+            width: 5,
+            height: 3,
+            bytes: 2,
+        };
+        assert_eq!(part1_in(puzzle, space), 6);
+        assert_eq!(part2_in(puzzle, space), "2,1");
+        let points = parse(puzzle);
+        assert_eq!(two_inner(&points, space), Point(2, 1));
+        assert_eq!(two_inner_astar(&points, space), Point(2, 1));
+        assert_eq!(two_binary_search_inner(&points, space), Point(2, 1));
+        assert_eq!(two_binary_search_map_inner(&points, 2, space), Point(2, 1));
+    }
 }
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use super::racetrack::Racetrack;
+use super::{manhattan, manhattan_diamond, Point};
+
+/// Going through walls from `start` to `end`, both on the track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cheat {
+    pub start: Point,
+    pub end: Point,
+    /// Picoseconds saved over following the track.
+    pub saved: usize,
+}
+
+impl Cheat {
+    /// Picoseconds spent cheating.
+    pub fn length(&self) -> usize {
+        manhattan(self.start, self.end)
+    }
+}
+
+/// The offsets a cheat of at most `radius` picoseconds can jump by.
+fn offsets(radius: usize) -> Vec<(isize, isize)> {
+    (2..=radius).flat_map(manhattan_diamond).collect()
+}
+
+fn cheats_on<'t>(
+    track: &'t Racetrack,
+    offsets: &'t [(isize, isize)],
+) -> impl Iterator<Item = Cheat> + 't {
+    track.tiles().flat_map(move |start| {
+        offsets.iter().filter_map(move |&(dr, dc)| {
+            let end = (
+                start.0.checked_add_signed(dr)?,
+                start.1.checked_add_signed(dc)?,
+            );
+            let saved = track.saving(start, end)?;
+            Some(Cheat { start, end, saved })
+        })
+    })
+}
+
+/// Number of cheats of at most `radius` picoseconds saving at least `min_save`.
+pub fn count_cheats(track: &Racetrack, radius: usize, min_save: usize) -> usize {
+    cheats_on(track, &offsets(radius))
+        .filter(|cheat| cheat.saved >= min_save)
+        .count()
+}
+
+/// Every cheat of at most `radius` picoseconds which saves any time, ordered by position.
+pub fn cheats(track: &Racetrack, radius: usize) -> Vec<Cheat> {
+    let mut cheats: Vec<Cheat> = cheats_on(track, &offsets(radius)).collect();
+    cheats.sort_unstable();
+    cheats
+}
+
+/// Number of cheats of at most `radius` picoseconds by the time they save.
+pub fn savings_histogram(track: &Racetrack, radius: usize) -> BTreeMap<usize, usize> {
+    let mut histogram = BTreeMap::new();
+    for cheat in cheats_on(track, &offsets(radius)) {
+        *histogram.entry(cheat.saved).or_insert(0) += 1;
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day20::{parse, two_inner};
+
+    #[test]
+    fn example_histograms() {
+        let track = Racetrack::parse(include_str!("test.txt"));
+        assert_eq!(
+            savings_histogram(&track, 2),
+            BTreeMap::from([
+                (2, 14),
+                (4, 14),
+                (6, 2),
+                (8, 4),
+                (10, 2),
+                (12, 3),
+                (20, 1),
+                (36, 1),
+                (38, 1),
+                (40, 1),
+                (64, 1),
+            ])
+        );
+        let at_least_50: Vec<(usize, usize)> = savings_histogram(&track, 20)
+            .into_iter()
+            .filter(|&(saved, _)| saved >= 50)
+            .collect();
+        assert_eq!(
+            at_least_50,
+            vec![
+                (50, 32),
+                (52, 31),
+                (54, 29),
+                (56, 39),
+                (58, 25),
+                (60, 23),
+                (62, 20),
+                (64, 19),
+                (66, 12),
+                (68, 14),
+                (70, 12),
+                (72, 22),
+                (74, 4),
+                (76, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn individual_cheats() {
+        let puzzle = include_str!("test.txt");
+        let track = Racetrack::parse(puzzle);
+        let best: Vec<Cheat> = cheats(&track, 2)
+            .into_iter()
+            .filter(|cheat| cheat.saved == 64)
+            .collect();
+        assert_eq!(best.len(), 1);
+        assert_eq!(best[0].length(), 2);
+
+        let input = parse(puzzle);
+        let far = cheats(&track, 30);
+        for min_save in [1, 50, 70] {
+            let count = far.iter().filter(|cheat| cheat.saved >= min_save).count();
+            assert_eq!(count, two_inner(&input, 30, min_save));
+        }
+    }
+}
@@ -3,6 +3,10 @@ use pathfinding::{matrix::Matrix, prelude::bfs};
 
 use crate::util::first_line_length;
 
+pub mod cheats;
+pub mod racetrack;
+pub mod rotated;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 enum Tile {
     #[default]
@@ -10,7 +14,8 @@ enum Tile {
     Track(usize),
 }
 
-type Point = (usize, usize);
+/// A `(row, column)` position.
+pub type Point = (usize, usize);
 type Input = (Matrix<Tile>, Point, Point);
 
 #[aoc_generator(day20)]
@@ -18,7 +23,8 @@ fn parse(input: &str) -> Input {
     let input = input.as_bytes().trim_ascii_end();
     let mut start = None;
     let mut finish = None;
-    let mut map = Matrix::<Tile>::new_square(first_line_length(input), Tile::Wall);
+    let rows = input.split(|ch| *ch == b'\n').count();
+    let mut map = Matrix::<Tile>::new(rows, first_line_length(input), Tile::Wall);
     for (x, row) in input.split(|ch| *ch == b'\n').enumerate() {
         for (y, &ch) in row.iter().enumerate() {
             match ch {
@@ -125,51 +131,26 @@ fn one_dual(input: &Input) -> usize {
     one_inner_dual(input, 100)
 }
 
-const fn manhattan_diamond<const RADIUS: usize, const LEN: usize>() -> [(isize, isize); LEN] {
-    assert!(LEN == 4 * RADIUS);
-    let mut i: usize = 0;
-    let mut out = [(0, 0); LEN];
-    while i < RADIUS {
-        out[i] = (i as isize, (RADIUS - i) as isize);
-        out[i + RADIUS] = ((RADIUS - i) as isize, -(i as isize));
-        out[i + 2 * RADIUS] = (-(i as isize), -((RADIUS - i) as isize));
-        out[i + 3 * RADIUS] = (-((RADIUS - i) as isize), i as isize);
-        i += 1;
+/// The offsets at exactly `radius` steps from the origin.
+fn manhattan_diamond(radius: usize) -> Vec<(isize, isize)> {
+    if radius == 0 {
+        return vec![(0, 0)];
     }
-    out
-}
-
-macro_rules! make_manhattan_diamond {
-    ($radius:expr) => {
-        manhattan_diamond::<$radius, { 4 * $radius }>()
-    };
+    let radius = radius as isize;
+    (0..radius)
+        .flat_map(|i| {
+            [
+                (i, radius - i),
+                (radius - i, -i),
+                (-i, -(radius - i)),
+                (-(radius - i), i),
+            ]
+        })
+        .collect()
 }
 
-const MANHATTAN_DIAMONDS: [&[(isize, isize)]; 21] = [
-    &[(0, 0)],
-    &make_manhattan_diamond!(1),
-    &make_manhattan_diamond!(2),
-    &make_manhattan_diamond!(3),
-    &make_manhattan_diamond!(4),
-    &make_manhattan_diamond!(5),
-    &make_manhattan_diamond!(6),
-    &make_manhattan_diamond!(7),
-    &make_manhattan_diamond!(8),
-    &make_manhattan_diamond!(9),
-    &make_manhattan_diamond!(10),
-    &make_manhattan_diamond!(11),
-    &make_manhattan_diamond!(12),
-    &make_manhattan_diamond!(13),
-    &make_manhattan_diamond!(14),
-    &make_manhattan_diamond!(15),
-    &make_manhattan_diamond!(16),
-    &make_manhattan_diamond!(17),
-    &make_manhattan_diamond!(18),
-    &make_manhattan_diamond!(19),
-    &make_manhattan_diamond!(20),
-];
-
-fn two_inner((map, start, finish): &Input, min_save: usize) -> usize {
+fn two_inner((map, start, finish): &Input, radius: usize, min_save: usize) -> usize {
+    let diamonds: Vec<Vec<(isize, isize)>> = (0..=radius).map(manhattan_diamond).collect();
     let mut map = map.clone();
     let track = bfs(
         start,
@@ -184,8 +165,8 @@ fn two_inner((map, start, finish): &Input, min_save: usize) -> usize {
 
     for (time, pos) in track.iter().enumerate() {
         map[*pos] = Tile::Track(time);
-        for cheat_duration in 2..=20 {
-            for target in MANHATTAN_DIAMONDS[cheat_duration]
+        for (cheat_duration, diamond) in diamonds.iter().enumerate().skip(2) {
+            for target in diamond
                 .iter()
                 .filter_map(|dir| map.move_in_direction(*pos, *dir))
             {
@@ -206,7 +187,35 @@ fn two_inner((map, start, finish): &Input, min_save: usize) -> usize {
 
 #[aoc(day20, part2, naive)]
 fn two(input: &Input) -> usize {
-    two_inner(input, 100)
+    two_inner(input, 20, 100)
+}
+
+#[aoc_generator(day20, part1, racetrack)]
+#[aoc_generator(day20, part2, racetrack)]
+#[aoc_generator(day20, part1, rotated)]
+#[aoc_generator(day20, part2, rotated)]
+fn parse_racetrack(puzzle: &str) -> racetrack::Racetrack {
+    racetrack::Racetrack::parse(puzzle)
+}
+
+#[aoc(day20, part1, racetrack)]
+fn one_racetrack(track: &racetrack::Racetrack) -> usize {
+    cheats::count_cheats(track, 2, 100)
+}
+
+#[aoc(day20, part2, racetrack)]
+fn two_racetrack(track: &racetrack::Racetrack) -> usize {
+    cheats::count_cheats(track, 20, 100)
+}
+
+#[aoc(day20, part1, rotated)]
+fn one_rotated(track: &racetrack::Racetrack) -> usize {
+    rotated::count_cheats(track, 2, 100)
+}
+
+#[aoc(day20, part2, rotated)]
+fn two_rotated(track: &racetrack::Racetrack) -> usize {
+    rotated::count_cheats(track, 20, 100)
 }
 
 pub fn part1(puzzle: &str) -> usize {
@@ -237,7 +246,7 @@ mod examples {
 
     #[test]
     fn example2() {
-        let res = two_inner(&parse(include_str!("test.txt")), 50);
+        let res = two_inner(&parse(include_str!("test.txt")), 20, 50);
         assert_eq!(
             res,
             [32, 31, 29, 39, 25, 23, 20, 19, 12, 14, 12, 22, 4, 3]
@@ -246,10 +255,26 @@ mod examples {
         );
     }
 
+    #[test]
+    fn example_racetrack() {
+        let track = parse_racetrack(include_str!("test.txt"));
+        assert_eq!(cheats::count_cheats(&track, 2, 20), 5);
+        assert_eq!(cheats::count_cheats(&track, 20, 50), 285);
+    }
+
+    #[test]
+    fn non_square() {
+        // Extra rows of wall below the example must not break parsing.
+        let tall = format!("{}\n###############", include_str!("test.txt"));
+        assert_eq!(one_inner(&parse(&tall), 20), 5);
+        assert_eq!(one_inner_dual(&parse(&tall), 20), 5);
+        assert_eq!(cheats::count_cheats(&parse_racetrack(&tall), 2, 20), 5);
+    }
+
     #[test]
     fn manhattan_test() {
         assert_eq!(
-            manhattan_diamond::<2, 8>().iter().sorted().collect_vec(),
+            manhattan_diamond(2).iter().sorted().collect_vec(),
             SHORTCUT_DIRECTIONS.iter().sorted().collect_vec()
         );
     }
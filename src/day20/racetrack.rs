@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+use pathfinding::matrix::Matrix;
+
+use super::{manhattan, Point};
+
+const UNREACHABLE: usize = usize::MAX;
+
+/// A racetrack on a rectangular grid, which may branch and offer several routes to the finish.
+#[derive(Debug, Clone)]
+pub struct Racetrack {
+    pub start: Point,
+    pub finish: Point,
+    from_start: Matrix<usize>,
+    to_finish: Matrix<usize>,
+}
+
+fn distances(track: &Matrix<bool>, from: Point) -> Matrix<usize> {
+    let mut distances = Matrix::new(track.rows, track.columns, UNREACHABLE);
+    distances[from] = 0;
+    let mut queue = VecDeque::from([from]);
+    while let Some(pos) = queue.pop_front() {
+        let distance = distances[pos] + 1;
+        for next in track.neighbours(pos, false) {
+            if track[next] && distances[next] == UNREACHABLE {
+                distances[next] = distance;
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+impl Racetrack {
+    pub fn parse(puzzle: &str) -> Self {
+        let lines: Vec<&[u8]> = puzzle
+            .as_bytes()
+            .trim_ascii_end()
+            .split(|&ch| ch == b'\n')
+            .collect();
+        let columns = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+        let mut track = Matrix::new(lines.len(), columns, false);
+        let (mut start, mut finish) = (None, None);
+        for (row, line) in lines.iter().enumerate() {
+            for (column, &ch) in line.iter().enumerate() {
+                match ch {
+                    b'S' => start = Some((row, column)),
+                    b'E' => finish = Some((row, column)),
+                    b'.' | b'#' => {}
+                    _ => unreachable!("invalid input character: {}", ch),
+                }
+                track[(row, column)] = ch != b'#';
+            }
+        }
+        let (start, finish) = (start.unwrap(), finish.unwrap());
+        Self {
+            start,
+            finish,
+            from_start: distances(&track, start),
+            to_finish: distances(&track, finish),
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.from_start.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.from_start.columns
+    }
+
+    /// Time of the fastest route without cheating.
+    pub fn best_time(&self) -> usize {
+        self.from_start[self.finish]
+    }
+
+    pub fn from_start(&self, pos: Point) -> Option<usize> {
+        let distance = *self.from_start.get(pos)?;
+        (distance != UNREACHABLE).then_some(distance)
+    }
+
+    pub fn to_finish(&self, pos: Point) -> Option<usize> {
+        let distance = *self.to_finish.get(pos)?;
+        (distance != UNREACHABLE).then_some(distance)
+    }
+
+    /// Track tiles connected to both the start and the finish, in row-major order.
+    pub fn tiles(&self) -> impl Iterator<Item = Point> + '_ {
+        self.from_start
+            .keys()
+            .filter(|&pos| self.from_start(pos).is_some() && self.to_finish(pos).is_some())
+    }
+
+    /// Time saved against the fastest route by cheating from `start` to `end`.
+    pub fn saving(&self, start: Point, end: Point) -> Option<usize> {
+        let time = self.from_start(start)? + manhattan(start, end) + self.to_finish(end)?;
+        self.best_time()
+            .checked_sub(time)
+            .filter(|&saved| saved > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let track = Racetrack::parse(include_str!("test.txt"));
+        assert_eq!(track.best_time(), 84);
+        assert_eq!(track.tiles().count(), 85);
+        assert_eq!(track.saving((7, 7), (7, 5)), Some(64));
+        assert_eq!(track.saving((1, 1), (1, 3)), None);
+    }
+
+    #[test]
+    fn rectangular_and_branching() {
+        let track = Racetrack::parse("#######\n#S...E#\n#.###.#\n#.....#\n#######");
+        assert_eq!((track.rows(), track.columns()), (5, 7));
+        assert_eq!(track.best_time(), 4);
+        assert_eq!(track.tiles().count(), 12);
+        assert_eq!(track.from_start((3, 4)), Some(5));
+        assert_eq!(track.to_finish((3, 2)), Some(5));
+        assert_eq!(track.saving((3, 3), (1, 3)), None);
+    }
+}
@@ -0,0 +1,159 @@
+use super::racetrack::Racetrack;
+use super::{manhattan, Point};
+
+/// Fenwick tree counting points in rectangles of the rotated grid.
+struct Fenwick2D {
+    width: usize,
+    counts: Vec<u32>,
+}
+
+impl Fenwick2D {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            counts: vec![0; width * height],
+        }
+    }
+
+    fn add(&mut self, u: usize, v: usize) {
+        let mut i = u + 1;
+        while i <= self.counts.len() / self.width {
+            let mut j = v + 1;
+            while j <= self.width {
+                self.counts[(i - 1) * self.width + j - 1] += 1;
+                j += j & j.wrapping_neg();
+            }
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Points with coordinates below `u` and `v`.
+    fn prefix(&self, u: usize, v: usize) -> u32 {
+        let mut total = 0;
+        let mut i = u.min(self.counts.len() / self.width);
+        while i > 0 {
+            let mut j = v.min(self.width);
+            while j > 0 {
+                total += self.counts[(i - 1) * self.width + j - 1];
+                j -= j & j.wrapping_neg();
+            }
+            i -= i & i.wrapping_neg();
+        }
+        total
+    }
+
+    /// Points with `u` in `us` and `v` in `vs`.
+    fn count(&self, us: (usize, usize), vs: (usize, usize)) -> u32 {
+        self.prefix(us.1, vs.1) + self.prefix(us.0, vs.0)
+            - self.prefix(us.0, vs.1)
+            - self.prefix(us.1, vs.0)
+    }
+}
+
+/// Number of cheats of at most `radius` picoseconds saving at least `min_save`.
+///
+/// A cheat from `a` to `b` qualifies when `from_start(a) + |a - b| + to_finish(b)` stays within
+/// `limit = best_time - min_save`. Ends with `to_finish(b) <= limit - radius - from_start(a)`
+/// qualify anywhere in the diamond around `a`, which is a square in the coordinates
+/// `(row + column, row - column)`: sweeping starts and ends by those thresholds, a 2D Fenwick
+/// tree counts them. The few ends whose distance lies in the `radius` wide band above get
+/// checked one by one. With `w` the most tiles sharing a distance to the finish, this takes
+/// `O(n (log² n + radius w))`: on a single-lane track `w` is 1, while branching or open tracks
+/// can approach the `O(n radius²)` of trying every offset.
+pub fn count_cheats(track: &Racetrack, radius: usize, min_save: usize) -> usize {
+    let Some(limit) = track.best_time().checked_sub(min_save.max(1)) else {
+        return 0;
+    };
+    let (limit, radius) = (limit as isize, radius as isize);
+    let tiles: Vec<Point> = track.tiles().collect();
+    let rotate = |(row, column): Point| (row + column, row + track.columns() - 1 - column);
+
+    let mut by_finish: Vec<Vec<Point>> = Vec::new();
+    for &tile in &tiles {
+        let distance = track.to_finish(tile).unwrap();
+        if by_finish.len() <= distance {
+            by_finish.resize(distance + 1, Vec::new());
+        }
+        by_finish[distance].push(tile);
+    }
+    let mut starts: Vec<(isize, Point)> = tiles
+        .iter()
+        .map(|&tile| {
+            (
+                limit - radius - track.from_start(tile).unwrap() as isize,
+                tile,
+            )
+        })
+        .collect();
+    starts.sort_unstable();
+
+    let side = track.rows() + track.columns() - 1;
+    let mut inserted = Fenwick2D::new(side, side);
+    let mut next_finish = 0;
+    let mut cheats = 0;
+    for (threshold, start) in starts {
+        // Ends far enough from the finish to qualify wherever they lie in the diamond.
+        while (next_finish as isize) <= threshold && next_finish < by_finish.len() {
+            for &end in &by_finish[next_finish] {
+                let (u, v) = rotate(end);
+                inserted.add(u, v);
+            }
+            next_finish += 1;
+        }
+        if threshold >= 0 {
+            let (u, v) = rotate(start);
+            let r = radius as usize;
+            let us = (u.saturating_sub(r), u + r + 1);
+            let vs = (v.saturating_sub(r), v + r + 1);
+            cheats += inserted.count(us, vs) as usize;
+        }
+
+        // Ends within the band, where the length of the cheat matters.
+        let band = (threshold + 1).max(0)..(threshold + radius + 1).min(by_finish.len() as isize);
+        for distance in band {
+            let slack = (threshold + radius - distance) as usize;
+            cheats += by_finish[distance as usize]
+                .iter()
+                .filter(|&&end| manhattan(start, end) <= slack)
+                .count();
+        }
+    }
+    cheats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day20::{cheats, one_inner_dual, parse, two_inner};
+
+    #[test]
+    fn matches_naive_and_dual() {
+        let input = parse(include_str!("test.txt"));
+        let track = Racetrack::parse(include_str!("test.txt"));
+        for min_save in [1, 2, 10, 20, 50, 64, 76, 77] {
+            assert_eq!(
+                count_cheats(&track, 2, min_save),
+                one_inner_dual(&input, min_save)
+            );
+            for radius in [2, 6, 20] {
+                assert_eq!(
+                    count_cheats(&track, radius, min_save),
+                    two_inner(&input, radius, min_save)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn branching() {
+        let track = Racetrack::parse("#########\n#S..#...#\n#.#...#.#\n#...#..E#\n#########");
+        for radius in [2, 3, 5] {
+            for min_save in [1, 2, 4] {
+                assert_eq!(
+                    count_cheats(&track, radius, min_save),
+                    cheats::count_cheats(&track, radius, min_save)
+                );
+            }
+        }
+    }
+}
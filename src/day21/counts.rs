@@ -0,0 +1,333 @@
+use num_bigint::{BigInt, BigUint};
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+use super::{dpad_lut_key, dpad_one_move, numpad_one_move, DPadPress};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow;
+
+/// Number type for press counts. Fixed-width counts report overflow instead of wrapping.
+pub trait PressCount: Clone {
+    fn from_u64(n: u64) -> Self;
+    fn is_zero(&self) -> bool;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+
+    fn dpad_lut(depth: usize) -> Result<Lut<Self>, Overflow> {
+        dpad_lut_pow(depth)
+    }
+}
+
+macro_rules! impl_press_count {
+    ($($t:ty),*) => {
+        $(impl PressCount for $t {
+            fn from_u64(n: u64) -> Self {
+                n.into()
+            }
+
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+
+            fn checked_add(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_add(*self, *other)
+            }
+
+            fn checked_mul(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_mul(*self, *other)
+            }
+        })*
+    };
+}
+
+impl_press_count!(u64, u128);
+
+impl PressCount for BigUint {
+    fn from_u64(n: u64) -> Self {
+        n.into()
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn dpad_lut(depth: usize) -> Result<Lut<Self>, Overflow> {
+        Ok(dpad_lut_recurrence(depth))
+    }
+}
+
+type Lut<C> = [C; 25];
+type Matrix<C> = [[C; 25]; 25];
+
+/// `transitions()[k][j]` counts how often the key pair `j` occurs when a robot types pair `k`,
+/// so that `build_dpad_lut(depth) = transitions() * build_dpad_lut(depth - 1)`.
+fn transitions<C: PressCount>() -> Matrix<C> {
+    let mut counts = [[0; 25]; 25];
+    for &from in DPadPress::values() {
+        for &to in DPadPress::values() {
+            let mut last_pos = DPadPress::Activate;
+            for &next in dpad_one_move(from, to) {
+                counts[dpad_lut_key(from, to)][dpad_lut_key(last_pos, next)] += 1;
+                last_pos = next;
+            }
+        }
+    }
+    counts.map(|row| row.map(C::from_u64))
+}
+
+fn mul_vec<C: PressCount>(matrix: &Matrix<C>, vector: &Lut<C>) -> Result<Lut<C>, Overflow> {
+    let mut res = std::array::from_fn(|_| C::from_u64(0));
+    for (res, row) in res.iter_mut().zip(matrix) {
+        for (a, b) in row.iter().zip(vector) {
+            *res = res
+                .checked_add(&a.checked_mul(b).ok_or(Overflow)?)
+                .ok_or(Overflow)?;
+        }
+    }
+    Ok(res)
+}
+
+fn mul_mat<C: PressCount>(a: &Matrix<C>, b: &Matrix<C>) -> Result<Matrix<C>, Overflow> {
+    let zero = C::from_u64(0);
+    let mut res: Matrix<C> = std::array::from_fn(|_| std::array::from_fn(|_| zero.clone()));
+    for (res_row, a_row) in res.iter_mut().zip(a) {
+        for (a, b_row) in a_row.iter().zip(b) {
+            if a.is_zero() {
+                continue;
+            }
+            for (res, b) in res_row.iter_mut().zip(b_row) {
+                *res = res
+                    .checked_add(&a.checked_mul(b).ok_or(Overflow)?)
+                    .ok_or(Overflow)?;
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Runtime version of `build_dpad_lut`, one step per robot.
+pub fn dpad_lut<C: PressCount>(depth: usize) -> Result<Lut<C>, Overflow> {
+    let transitions = transitions();
+    let mut lut = std::array::from_fn(|_| C::from_u64(1));
+    for _ in 0..depth {
+        lut = mul_vec(&transitions, &lut)?;
+    }
+    Ok(lut)
+}
+
+/// Same as [`dpad_lut`] in `O(log depth)` matrix products.
+pub fn dpad_lut_pow<C: PressCount>(mut depth: usize) -> Result<Lut<C>, Overflow> {
+    let mut power = transitions();
+    let mut lut = std::array::from_fn(|_| C::from_u64(1));
+    while depth > 0 {
+        if depth & 1 == 1 {
+            lut = mul_vec(&power, &lut)?;
+        }
+        depth >>= 1;
+        if depth > 0 {
+            power = mul_mat(&power, &power)?;
+        }
+    }
+    Ok(lut)
+}
+
+/// Finds the shortest linear recurrence `lut(d + k) = sum(c[i] * lut(d + i))` satisfied by the
+/// LUTs, returning `lut(0..k)` and `c`.
+fn lut_recurrence() -> (Vec<Lut<BigInt>>, Vec<BigInt>) {
+    let luts: Vec<Lut<BigInt>> = (0..=25)
+        .map(|depth| dpad_lut::<u64>(depth).unwrap().map(BigInt::from))
+        .collect();
+    // Gaussian elimination, tracking how each reduced vector combines the original LUTs.
+    let mut basis: Vec<(Vec<BigRational>, usize, Vec<BigRational>)> = Vec::new();
+    for (k, lut) in luts.iter().enumerate() {
+        let mut vector: Vec<BigRational> = lut.iter().cloned().map(BigRational::from).collect();
+        let mut combination = vec![BigRational::zero(); k + 1];
+        combination[k] = BigRational::one();
+        for (basis_vector, pivot, basis_combination) in &basis {
+            if vector[*pivot].is_zero() {
+                continue;
+            }
+            let factor = &vector[*pivot] / &basis_vector[*pivot];
+            for (v, b) in vector.iter_mut().zip(basis_vector) {
+                *v -= &factor * b;
+            }
+            for (c, b) in combination.iter_mut().zip(basis_combination) {
+                *c -= &factor * b;
+            }
+        }
+        match vector.iter().position(|v| !v.is_zero()) {
+            Some(pivot) => basis.push((vector, pivot, combination)),
+            None => {
+                let coefficients = combination[..k]
+                    .iter()
+                    .map(|c| {
+                        assert!(c.is_integer());
+                        -c.to_integer()
+                    })
+                    .collect();
+                return (luts[..k].to_vec(), coefficients);
+            }
+        }
+    }
+    unreachable!("the LUTs live in a 25-dimensional space")
+}
+
+/// `a^2 mod (x^k - sum(c[i] * x^i))` for a polynomial given by its `k` coefficients.
+fn square_mod(a: &[BigInt], c: &[BigInt]) -> Vec<BigInt> {
+    let k = c.len();
+    let mut square = vec![BigInt::zero(); 2 * k - 1];
+    for (i, a_i) in a.iter().enumerate().filter(|(_, a)| !a.is_zero()) {
+        square[2 * i] += a_i * a_i;
+        for (j, a_j) in a.iter().enumerate().skip(i + 1) {
+            square[i + j] += (a_i * a_j) << 1;
+        }
+    }
+    reduce(square, c)
+}
+
+fn reduce(mut product: Vec<BigInt>, c: &[BigInt]) -> Vec<BigInt> {
+    let k = c.len();
+    for i in (k..product.len()).rev() {
+        let top = std::mem::take(&mut product[i]);
+        if top.is_zero() {
+            continue;
+        }
+        for (j, c) in c.iter().enumerate() {
+            product[i - k + j] += &top * c;
+        }
+    }
+    product.truncate(k);
+    product
+}
+
+/// Big integer LUT for any depth via Kitamasa's method on the LUT recurrence, which needs far
+/// fewer big multiplications than squaring the full transition matrix.
+pub fn dpad_lut_recurrence(depth: usize) -> Lut<BigUint> {
+    let (luts, c) = lut_recurrence();
+    let k = c.len();
+    let to_unsigned = |lut: &Lut<BigInt>| lut.clone().map(|n| n.to_biguint().unwrap());
+    if depth < k {
+        return to_unsigned(&luts[depth]);
+    }
+    // Coefficients of x^depth modulo the characteristic polynomial.
+    let mut power = vec![BigInt::zero(); k];
+    power[0] = BigInt::one();
+    for bit in (0..usize::BITS - depth.leading_zeros()).rev() {
+        power = square_mod(&power, &c);
+        if depth >> bit & 1 == 1 {
+            power.insert(0, BigInt::zero());
+            power = reduce(power, &c);
+        }
+    }
+    let mut lut: Lut<BigInt> = std::array::from_fn(|_| BigInt::zero());
+    for (coefficient, base) in power.iter().zip(&luts) {
+        for (n, b) in lut.iter_mut().zip(base) {
+            *n += coefficient * b;
+        }
+    }
+    debug_assert!(lut.iter().all(|n| !n.is_negative()));
+    to_unsigned(&lut)
+}
+
+pub fn code_presses<C: PressCount>(code: &[u8], dpad_lut: &Lut<C>) -> Result<C, Overflow> {
+    let mut res = C::from_u64(0);
+    let mut prev_key = b'A';
+    for &key in code {
+        let mut last_pos = DPadPress::Activate;
+        for next in numpad_one_move(prev_key, key) {
+            res = res
+                .checked_add(&dpad_lut[dpad_lut_key(last_pos, next)])
+                .ok_or(Overflow)?;
+            last_pos = next;
+        }
+        prev_key = key;
+    }
+    Ok(res)
+}
+
+/// Sum of code complexities for any number of codes of any length at any robot depth.
+pub fn complexity<C: PressCount>(input: &str, depth: usize) -> Result<C, Overflow> {
+    let lut = C::dpad_lut(depth)?;
+    let mut res = C::from_u64(0);
+    for code in input.lines().filter(|code| !code.is_empty()) {
+        let code = code.as_bytes();
+        let value = code
+            .iter()
+            .filter(|c| c.is_ascii_digit())
+            .try_fold(C::from_u64(0), |value, c| {
+                value
+                    .checked_mul(&C::from_u64(10))?
+                    .checked_add(&C::from_u64((c - b'0') as u64))
+            })
+            .ok_or(Overflow)?;
+        let presses: C = code_presses(code, &lut)?;
+        let score = presses.checked_mul(&value).ok_or(Overflow)?;
+        res = res.checked_add(&score).ok_or(Overflow)?;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day21::build_dpad_lut;
+
+    #[test]
+    fn matches_const_lut() {
+        for depth in [1, 2, 25] {
+            let expected = build_dpad_lut(depth).map(|n| n as u64);
+            assert_eq!(dpad_lut::<u64>(depth), Ok(expected));
+            assert_eq!(dpad_lut_pow::<u64>(depth), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn pow_matches_iteration() {
+        for depth in 0..80 {
+            assert_eq!(dpad_lut::<u128>(depth), dpad_lut_pow::<u128>(depth));
+        }
+        for depth in [0, 1, 10, 11, 12, 100, 500, 1001] {
+            let expected = dpad_lut::<BigUint>(depth);
+            assert_eq!(dpad_lut_pow::<BigUint>(depth), expected);
+            assert_eq!(Ok(dpad_lut_recurrence(depth)), expected);
+        }
+    }
+
+    #[test]
+    fn detects_overflow() {
+        let input = include_str!("test.txt");
+        for depth in 0..120 {
+            let exact = complexity::<BigUint>(input, depth).unwrap();
+            assert_eq!(
+                complexity::<u64>(input, depth).ok().map(BigUint::from),
+                Some(exact.clone()).filter(|n| n.bits() <= 64)
+            );
+            assert_eq!(
+                complexity::<u128>(input, depth).ok().map(BigUint::from),
+                Some(exact).filter(|n| n.bits() <= 128)
+            );
+        }
+
+        // The numeric value alone does not fit a u64.
+        let long = "98765432109876543210A";
+        assert_eq!(complexity::<u64>(long, 0), Err(Overflow));
+        let exact = complexity::<BigUint>(long, 0).unwrap();
+        assert_eq!(complexity::<u128>(long, 0).map(BigUint::from), Ok(exact));
+    }
+
+    #[test]
+    fn example() {
+        let input = include_str!("test.txt");
+        assert_eq!(complexity::<u64>(input, 2), Ok(126384));
+        assert_eq!(complexity::<u64>(input, 25), Ok(154115708116294));
+    }
+}
@@ -0,0 +1,491 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// The keys a robot arm can be steered with, in table order.
+const CONTROLS: [u8; 5] = [b'^', b'v', b'<', b'>', b'A'];
+const ACTIVATE: usize = 4;
+
+type ControlCosts = [[Option<u64>; 5]; 5];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keypad {
+    width: usize,
+    keys: Vec<Option<u8>>,
+}
+
+impl Keypad {
+    /// Parses a layout given as lines of keys, with spaces marking gaps.
+    pub fn new(layout: &str) -> Self {
+        let width = layout.lines().map(str::len).max().unwrap_or(0);
+        let mut keys = Vec::new();
+        for line in layout.lines() {
+            let line = line.as_bytes();
+            keys.extend((0..width).map(|x| match line.get(x) {
+                None | Some(b' ') => None,
+                Some(&key) => Some(key),
+            }));
+        }
+        let pad = Self { width, keys };
+        for key in pad.keys() {
+            assert_eq!(
+                pad.keys().filter(|&k| k == key).count(),
+                1,
+                "key {:?} appears more than once",
+                key as char
+            );
+        }
+        pad
+    }
+
+    pub fn numeric() -> Self {
+        Self::new("789\n456\n123\n 0A")
+    }
+
+    pub fn directional() -> Self {
+        Self::new(" ^A\n<v>")
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = u8> + '_ {
+        self.keys.iter().flatten().copied()
+    }
+
+    pub fn position(&self, key: u8) -> Option<(usize, usize)> {
+        let cell = self.cell(key)?;
+        Some((cell % self.width, cell / self.width))
+    }
+
+    pub fn key_at(&self, x: usize, y: usize) -> Option<u8> {
+        if x >= self.width {
+            return None;
+        }
+        *self.keys.get(y * self.width + x)?
+    }
+
+    fn cell(&self, key: u8) -> Option<usize> {
+        self.keys.iter().position(|&k| k == Some(key))
+    }
+
+    fn step(&self, cell: usize, control: usize) -> Option<usize> {
+        let (x, y) = (cell % self.width, cell / self.width);
+        let next = match CONTROLS[control] {
+            b'^' if y > 0 => cell - self.width,
+            b'v' => cell + self.width,
+            b'<' if x > 0 => cell - 1,
+            b'>' if x + 1 < self.width => cell + 1,
+            _ => return None,
+        };
+        self.keys.get(next)?.map(|_| next)
+    }
+
+    fn step_key(&self, cell: usize, key: u8) -> Option<usize> {
+        self.step(cell, CONTROLS.iter().position(|&c| c == key)?)
+    }
+
+    /// Cost and press sequence for moving the arm over this pad between any two cells and
+    /// pressing the target, given the cost of each pair of consecutive controller presses.
+    fn moves(&self, controller: &ControlCosts) -> (Vec<Option<u64>>, Vec<Vec<u8>>) {
+        let cells = self.keys.len();
+        let states = cells * CONTROLS.len();
+        let mut costs = vec![None; cells * cells];
+        let mut paths = vec![Vec::new(); cells * cells];
+        for from in (0..cells).filter(|&c| self.keys[c].is_some()) {
+            // Shortest presses to reach each (cell, last control pressed) state.
+            let mut distances = vec![None; states];
+            let mut predecessors = vec![usize::MAX; states];
+            let mut queue = BinaryHeap::new();
+            queue.push(Reverse((0, from * CONTROLS.len() + ACTIVATE, usize::MAX)));
+            while let Some(Reverse((distance, state, predecessor))) = queue.pop() {
+                if distances[state].is_some() {
+                    continue;
+                }
+                distances[state] = Some(distance);
+                predecessors[state] = predecessor;
+                let (cell, last) = (state / CONTROLS.len(), state % CONTROLS.len());
+                for (control, cost) in controller[last][..ACTIVATE].iter().enumerate() {
+                    if let (Some(next), Some(cost)) = (self.step(cell, control), cost) {
+                        let next = next * CONTROLS.len() + control;
+                        let distance =
+                            u64::checked_add(distance, *cost).expect("press counts overflow a u64");
+                        queue.push(Reverse((distance, next, state)));
+                    }
+                }
+            }
+            for to in 0..cells {
+                let Some((cost, mut state)) = (0..CONTROLS.len())
+                    .filter_map(|last| {
+                        let state = to * CONTROLS.len() + last;
+                        Some((distances[state]? + controller[last][ACTIVATE]?, state))
+                    })
+                    .min()
+                else {
+                    continue;
+                };
+                let path = &mut paths[from * cells + to];
+                path.push(b'A');
+                while predecessors[state] != usize::MAX {
+                    path.push(CONTROLS[state % CONTROLS.len()]);
+                    state = predecessors[state];
+                }
+                path.reverse();
+                costs[from * cells + to] = Some(cost);
+            }
+        }
+        (costs, paths)
+    }
+
+    fn control_costs(&self, costs: &[Option<u64>]) -> ControlCosts {
+        let cells = self.keys.len();
+        let mut control_costs = [[None; 5]; 5];
+        for (from, from_key) in CONTROLS.into_iter().enumerate() {
+            for (to, to_key) in CONTROLS.into_iter().enumerate() {
+                let (Some(from_cell), Some(to_cell)) = (self.cell(from_key), self.cell(to_key))
+                else {
+                    panic!("a pad steering a robot needs the keys ^, v, <, > and A");
+                };
+                control_costs[from][to] = costs[from_cell * cells + to_cell];
+            }
+        }
+        control_costs
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Level {
+    pad: Keypad,
+    costs: Vec<Option<u64>>,
+    paths: Vec<Vec<u8>>,
+}
+
+impl Level {
+    fn index(&self, from: u8, to: u8) -> Option<usize> {
+        Some(self.pad.cell(from)? * self.pad.keys.len() + self.pad.cell(to)?)
+    }
+
+    fn cost(&self, from: u8, to: u8) -> Option<u64> {
+        self.costs[self.index(from, to)?]
+    }
+
+    fn path(&self, from: u8, to: u8) -> &[u8] {
+        &self.paths[self.index(from, to).unwrap()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The press at this index is not a key of the human's directional pad.
+    UnknownKey { press: usize },
+    /// The press at this index moved an arm off the pad or over a gap. Pad 0 is the door.
+    AimedAtGap { press: usize, pad: usize },
+}
+
+/// A door keypad operated through a chain of robots, each steered from a directional pad.
+#[derive(Debug, Clone)]
+pub struct KeypadChain {
+    /// The door pad followed by the robot pads, each steered by the next.
+    levels: Vec<Level>,
+}
+
+impl KeypadChain {
+    /// `robot_pads` are the directional pads robot arms hover over, starting next to the door.
+    /// The human presses keys on one more directional pad whose layout does not matter.
+    ///
+    /// Press counts are u64, so this panics once a single move costs more than that holds. With
+    /// the puzzle's pads that happens from 47 robots on; `counts::complexity` takes any depth.
+    pub fn new(door: Keypad, robot_pads: &[Keypad]) -> Self {
+        let mut controller = [[Some(1); 5]; 5];
+        let mut levels = Vec::with_capacity(robot_pads.len() + 1);
+        for pad in robot_pads.iter().rev().chain([&door]) {
+            let (costs, paths) = pad.moves(&controller);
+            if levels.len() < robot_pads.len() {
+                controller = pad.control_costs(&costs);
+            }
+            levels.push(Level {
+                pad: pad.clone(),
+                costs,
+                paths,
+            });
+        }
+        levels.reverse();
+        Self { levels }
+    }
+
+    pub fn puzzle(robots: usize) -> Self {
+        Self::new(Keypad::numeric(), &vec![Keypad::directional(); robots])
+    }
+
+    /// Minimal human presses to type `code`, or `None` if some key cannot be reached or the
+    /// count overflows a u64.
+    pub fn press_count(&self, code: &[u8]) -> Option<u64> {
+        let door = &self.levels[0];
+        let mut presses: u64 = 0;
+        for (from, to) in [b'A'].iter().chain(code).zip(code) {
+            presses = presses.checked_add(door.cost(*from, *to)?)?;
+        }
+        Some(presses)
+    }
+
+    /// One shortest sequence of human presses typing `code`, expanded lazily.
+    pub fn presses<'a>(&'a self, code: &'a [u8]) -> Option<Presses<'a>> {
+        let remaining = self.press_count(code)?;
+        Some(Presses {
+            levels: &self.levels,
+            stack: vec![Frame {
+                level: 0,
+                keys: code,
+                index: 0,
+                arm: b'A',
+            }],
+            remaining,
+        })
+    }
+
+    /// Runs human presses through the chain, returning the keys typed on the door.
+    pub fn replay(&self, presses: impl IntoIterator<Item = u8>) -> Result<Vec<u8>, ReplayError> {
+        let mut arms: Vec<usize> = self
+            .levels
+            .iter()
+            .map(|level| level.pad.cell(b'A').unwrap())
+            .collect();
+        let mut typed = Vec::new();
+        for (press, mut key) in presses.into_iter().enumerate() {
+            if !CONTROLS.contains(&key) {
+                return Err(ReplayError::UnknownKey { press });
+            }
+            for pad in (0..self.levels.len()).rev() {
+                if key != b'A' {
+                    arms[pad] = self.levels[pad]
+                        .pad
+                        .step_key(arms[pad], key)
+                        .ok_or(ReplayError::AimedAtGap { press, pad })?;
+                    break;
+                }
+                key = self.levels[pad].pad.keys[arms[pad]].unwrap();
+                if pad == 0 {
+                    typed.push(key);
+                }
+            }
+        }
+        Ok(typed)
+    }
+
+    /// Sum of press counts times numeric value over one code per line, or `None` if some code
+    /// cannot be typed or the sum overflows a u64.
+    pub fn complexity(&self, codes: &str) -> Option<u64> {
+        codes
+            .lines()
+            .filter(|code| !code.is_empty())
+            .try_fold(0u64, |sum, code| {
+                let code = code.as_bytes();
+                let value = code
+                    .iter()
+                    .filter(|c| c.is_ascii_digit())
+                    .try_fold(0u64, |value, c| {
+                        value.checked_mul(10)?.checked_add((c - b'0') as u64)
+                    })?;
+                sum.checked_add(self.press_count(code)?.checked_mul(value)?)
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Frame<'a> {
+    level: usize,
+    keys: &'a [u8],
+    index: usize,
+    /// The key the arm at this level is currently aimed at.
+    arm: u8,
+}
+
+/// Human presses, produced by expanding each level's moves only when they are reached.
+#[derive(Debug, Clone)]
+pub struct Presses<'a> {
+    levels: &'a [Level],
+    stack: Vec<Frame<'a>>,
+    remaining: u64,
+}
+
+impl Presses<'_> {
+    /// Advances past `n` presses without visiting them.
+    pub fn skip_presses(&mut self, mut n: u64) {
+        n = n.min(self.remaining);
+        self.remaining -= n;
+        while n > 0 {
+            let frame = self.stack.last_mut().unwrap();
+            if frame.index == frame.keys.len() {
+                self.stack.pop();
+                continue;
+            }
+            let key = frame.keys[frame.index];
+            frame.index += 1;
+            let from = std::mem::replace(&mut frame.arm, key);
+            if frame.level == self.levels.len() {
+                n -= 1;
+                continue;
+            }
+            let level = &self.levels[frame.level];
+            let cost = level.cost(from, key).unwrap();
+            if cost <= n {
+                n -= cost;
+            } else {
+                let frame = Frame {
+                    level: frame.level + 1,
+                    keys: level.path(from, key),
+                    index: 0,
+                    arm: b'A',
+                };
+                self.stack.push(frame);
+            }
+        }
+    }
+}
+
+impl Iterator for Presses<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            if frame.index == frame.keys.len() {
+                self.stack.pop();
+                continue;
+            }
+            let key = frame.keys[frame.index];
+            frame.index += 1;
+            let from = std::mem::replace(&mut frame.arm, key);
+            if frame.level == self.levels.len() {
+                self.remaining -= 1;
+                return Some(key);
+            }
+            let frame = Frame {
+                level: frame.level + 1,
+                keys: self.levels[frame.level].path(from, key),
+                index: 0,
+                arm: b'A',
+            };
+            self.stack.push(frame);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from(self.remaining) {
+            Ok(remaining) => (remaining, Some(remaining)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<u8> {
+        self.skip_presses(n as u64);
+        self.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day21::input_code_recursive;
+    use rustc_hash::FxHashMap;
+
+    #[test]
+    fn matches_fixed_pads() {
+        let mut memo = FxHashMap::default();
+        for robots in 1..6 {
+            let chain = KeypadChain::puzzle(robots);
+            for code in [b"029A", b"980A", b"179A", b"456A", b"379A"] {
+                assert_eq!(
+                    chain.press_count(code),
+                    Some(input_code_recursive(*code, robots, &mut memo) as u64)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn direct_presses() {
+        assert_eq!(
+            KeypadChain::puzzle(0).press_count(b"029A"),
+            Some("<A^A^^>AvvvA".len() as u64)
+        );
+    }
+
+    #[test]
+    fn presses_replay_to_code() {
+        let chain = KeypadChain::puzzle(3);
+        for code in [b"029A", b"980A", b"179A", b"456A", b"379A"] {
+            let presses: Vec<u8> = chain.presses(code).unwrap().collect();
+            assert_eq!(presses.len() as u64, chain.press_count(code).unwrap());
+            assert_eq!(chain.replay(presses), Ok(code.to_vec()));
+        }
+    }
+
+    #[test]
+    fn seek_presses() {
+        let chain = KeypadChain::puzzle(4);
+        let full: Vec<u8> = chain.presses(b"379A").unwrap().collect();
+        for n in [0, 1, 17, 123, full.len() - 1, full.len()] {
+            assert_eq!(chain.presses(b"379A").unwrap().nth(n), full.get(n).copied());
+        }
+        let half = full.len() / 2;
+        let mut rest = chain.presses(b"379A").unwrap();
+        rest.skip_presses(half as u64);
+        assert_eq!(rest.size_hint().0, full.len() - half);
+        assert!(rest.eq(full[half..].iter().copied()));
+
+        let deep = KeypadChain::puzzle(25);
+        let mut presses = deep.presses(b"029A").unwrap();
+        presses.skip_presses(deep.press_count(b"029A").unwrap() - 1);
+        assert_eq!(presses.collect::<Vec<_>>(), vec![b'A']);
+    }
+
+    #[test]
+    fn replay_detects_gap() {
+        let chain = KeypadChain::puzzle(2);
+        assert_eq!(
+            chain.replay(*b"<<"),
+            Err(ReplayError::AimedAtGap { press: 1, pad: 2 })
+        );
+        assert_eq!(
+            chain.replay(*b"vA"),
+            Err(ReplayError::AimedAtGap { press: 1, pad: 1 })
+        );
+        assert_eq!(
+            chain.replay(*b"A?"),
+            Err(ReplayError::UnknownKey { press: 1 })
+        );
+    }
+
+    #[test]
+    fn custom_layouts() {
+        // Phone-style door pad with longer codes, steered through a flipped directional pad.
+        let door = Keypad::new("123\n456\n789\n 0A");
+        let flipped = Keypad::new("<v>\n ^A");
+        let chain = KeypadChain::new(door, &[flipped.clone(), flipped]);
+        let codes = "12A\n8080A\n";
+        assert!(chain.complexity(codes) > Some(0));
+        assert_eq!(chain.press_count(b"B"), None);
+
+        // A door pad whose keys are not connected cannot be typed on.
+        let split = KeypadChain::new(Keypad::new("1 2\nA  "), &[]);
+        assert_eq!(split.press_count(b"1"), Some(2));
+        assert_eq!(split.press_count(b"2"), None);
+    }
+
+    #[test]
+    fn deepest_chain() {
+        assert!(KeypadChain::puzzle(46).press_count(b"A").is_some());
+        let codes = include_str!("test.txt");
+        assert_eq!(
+            KeypadChain::puzzle(25).complexity(codes),
+            Some(154115708116294)
+        );
+        assert_eq!(KeypadChain::puzzle(40).complexity(codes), None);
+        assert_eq!(
+            KeypadChain::new(Keypad::numeric(), &[]).complexity("98765432109876543210A"),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "press counts overflow a u64")]
+    fn too_deep() {
+        KeypadChain::puzzle(47);
+    }
+}
@@ -4,6 +4,9 @@ use aoc_runner_derive::{aoc, aoc_generator};
 use arrayvec::ArrayVec;
 use rustc_hash::FxHashMap;
 
+pub mod counts;
+pub mod keypad;
+
 type Input = [[u8; 4]; 5];
 
 pub fn part1(puzzle: &str) -> u64 {
@@ -686,7 +689,7 @@ unsafe fn code_lut_simd_asm<const DEPTH: usize>(input: &str) -> u64 {
         let score: __m256i;
         std::arch::asm!(
             "vpcmpeqd {simd_tmp}, {simd_tmp}, {simd_tmp}", // set all to 1
-            "vpgatherdd {simd1}, ymmword ptr [{input}+{simd2}*1], {simd_tmp}",
+            "vpgatherdd {simd1}, dword ptr [{input}+{simd2}*1], {simd_tmp}",
             "vpsrld {simd2}, {simd1}, 12",
             "vpxor {simd1}, {simd2}, {simd1}",
             "vpcmpeqd {simd_tmp}, {simd_tmp}, {simd_tmp}", // set all to 1 again, was cleared by gather
@@ -696,8 +699,8 @@ unsafe fn code_lut_simd_asm<const DEPTH: usize>(input: &str) -> u64 {
             "vextracti128 {indices2}, {simd1}, 0",
             "vpcmpeqd {simd_tmp}, {simd_tmp}, {simd_tmp}",
             "vpcmpeqd {simd_tmp2}, {simd_tmp2}, {simd_tmp2}",
-            "vpgatherdq {simd1}, ymmword ptr [{lut}+{indices1}*8], {simd_tmp}",
-            "vpgatherdq {simd2}, ymmword ptr [{lut}+{indices2}*8], {simd_tmp2}",
+            "vpgatherdq {simd1}, qword ptr [{lut}+{indices1}*8], {simd_tmp}",
+            "vpgatherdq {simd2}, qword ptr [{lut}+{indices2}*8], {simd_tmp2}",
             "vpermq {simd_tmp}, {simd1}, {shuf1}",
             "vpaddq {simd1}, {simd_tmp}, {simd1}",
             "vpermq {simd_tmp}, {simd1}, {shuf2}",
@@ -733,6 +736,21 @@ fn two_code_lut_simd_asm(input: &str) -> u64 {
     unsafe { code_lut_simd_asm::<25>(input) }
 }
 
+#[aoc(day21, part1, keypad_chain)]
+fn one_keypad_chain(input: &str) -> u64 {
+    keypad::KeypadChain::puzzle(2).complexity(input).unwrap()
+}
+
+#[aoc(day21, part2, keypad_chain)]
+fn two_keypad_chain(input: &str) -> u64 {
+    keypad::KeypadChain::puzzle(25).complexity(input).unwrap()
+}
+
+#[aoc(day21, part2, matrix_pow)]
+fn two_matrix_pow(input: &str) -> u64 {
+    counts::complexity::<u64>(input, 25).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -771,6 +789,14 @@ mod tests {
         assert_eq!(part2(input), 154115708116294);
     }
 
+    #[test]
+    fn test_keypad_chain() {
+        let input = include_str!("test.txt");
+        assert_eq!(one_keypad_chain(input), 126384);
+        assert_eq!(two_keypad_chain(input), 154115708116294);
+        assert_eq!(two_matrix_pow(input), 154115708116294);
+    }
+
     #[test]
     fn test_numpad_one_move_array() {
         for from in NUMPAD_KEYS {
@@ -0,0 +1,225 @@
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+use super::{add_sequence_values_array, next_secret, SECRET_MASK, SEQUENCE_VALUE_TABLE_SIZE};
+
+/// Number of buyers whose secrets are advanced together.
+pub const LANES: usize = 8;
+
+type Lanes = [u32; LANES];
+
+#[inline(always)]
+fn step_scalar(secrets: &mut Lanes) {
+    for secret in secrets {
+        *secret = next_secret(*secret);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+macro_rules! step_avx2 {
+    ($secrets:expr, $mask:expr) => {{
+        let s = $secrets;
+        let s = _mm256_and_si256(_mm256_xor_si256(_mm256_slli_epi32::<6>(s), s), $mask);
+        let s = _mm256_xor_si256(_mm256_srli_epi32::<5>(s), s);
+        _mm256_and_si256(_mm256_xor_si256(_mm256_slli_epi32::<11>(s), s), $mask)
+    }};
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn advance_avx2(secrets: &mut Lanes, steps: usize) {
+    let mask = _mm256_set1_epi32(SECRET_MASK as i32);
+    let mut s = _mm256_loadu_si256(secrets.as_ptr().cast());
+    for _ in 0..steps {
+        s = step_avx2!(s, mask);
+    }
+    _mm256_storeu_si256(secrets.as_mut_ptr().cast(), s);
+}
+
+fn advance_scalar(secrets: &mut Lanes, steps: usize) {
+    for _ in 0..steps {
+        step_scalar(secrets);
+    }
+}
+
+/// Advances every lane by `steps` secrets, with AVX2 where the CPU has it.
+pub fn advance(secrets: &mut Lanes, steps: usize) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { advance_avx2(secrets, steps) };
+    }
+    advance_scalar(secrets, steps)
+}
+
+/// Secrets generated per buyer, after the initial one.
+const STEPS: usize = 2000;
+
+/// For every lane, the index of the last four price changes shifted left by four, or'ed with
+/// the price after them.
+type Sales = [u32; LANES];
+
+/// The latest price and the index of the last four price changes of every lane.
+struct Windows {
+    prices: [u32; LANES],
+    indices: [u32; LANES],
+}
+
+impl Windows {
+    #[inline(always)]
+    fn new(secrets: &Lanes) -> Self {
+        Self {
+            prices: secrets.map(|secret| secret % 10),
+            indices: [0; LANES],
+        }
+    }
+
+    #[inline(always)]
+    fn push(&mut self, secrets: &Lanes) -> Sales {
+        let mut sales = [0; LANES];
+        for (lane, secret) in secrets.iter().enumerate() {
+            let price = secret % 10;
+            let change = price + 9 - self.prices[lane];
+            self.indices[lane] =
+                (self.indices[lane] * 19 + change) % SEQUENCE_VALUE_TABLE_SIZE as u32;
+            self.prices[lane] = price;
+            sales[lane] = self.indices[lane] << 4 | price;
+        }
+        sales
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn sales_avx2(mut secrets: Lanes, sales: &mut [Sales]) {
+    let mask = _mm256_set1_epi32(SECRET_MASK as i32);
+    let mut s = _mm256_loadu_si256(secrets.as_ptr().cast());
+    let mut windows = Windows::new(&secrets);
+    for step in 0..STEPS {
+        s = step_avx2!(s, mask);
+        _mm256_storeu_si256(secrets.as_mut_ptr().cast(), s);
+        let window = windows.push(&secrets);
+        if step >= 3 {
+            sales[step - 3] = window;
+        }
+    }
+}
+
+fn sales_scalar(mut secrets: Lanes, sales: &mut [Sales]) {
+    let mut windows = Windows::new(&secrets);
+    for step in 0..STEPS {
+        step_scalar(&mut secrets);
+        let window = windows.push(&secrets);
+        if step >= 3 {
+            sales[step - 3] = window;
+        }
+    }
+}
+
+/// Fills `sales` with every complete window of price changes of the lanes, in order.
+fn sales(secrets: Lanes, sales: &mut [Sales]) {
+    assert_eq!(sales.len(), STEPS - 3);
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return unsafe { sales_avx2(secrets, sales) };
+    }
+    sales_scalar(secrets, sales)
+}
+
+/// Adds the first price of every change sequence of the buyers `first_buyer..`, one per lane.
+///
+/// The table is shared with `add_sequence_values_array`: it remembers the last buyer which sold
+/// on each sequence, so the lanes are recorded one after the other.
+fn record(sales: &[Sales], first_buyer: u16, sequence_value: &mut [(u16, u16)]) {
+    for lane in 0..LANES {
+        let buyer = first_buyer + lane as u16;
+        for sale in sales {
+            let entry = &mut sequence_value[(sale[lane] >> 4) as usize];
+            if entry.0 != buyer {
+                *entry = (buyer, entry.1 + (sale[lane] & 15) as u16);
+            }
+        }
+    }
+}
+
+fn parse(puzzle: &str) -> Vec<u32> {
+    puzzle.lines().map(|l| l.parse::<u32>().unwrap()).collect()
+}
+
+pub fn part1(puzzle: &str) -> u64 {
+    let secrets = parse(puzzle);
+    let mut chunks = secrets.chunks_exact(LANES);
+    let mut res: u64 = 0;
+    for chunk in &mut chunks {
+        let mut lanes: Lanes = chunk.try_into().unwrap();
+        advance(&mut lanes, 2000);
+        res += lanes.iter().map(|&secret| secret as u64).sum::<u64>();
+    }
+    for &secret in chunks.remainder() {
+        res += (0..2000).fold(secret, |secret, _| next_secret(secret)) as u64;
+    }
+    res
+}
+
+pub fn part2(puzzle: &str) -> u16 {
+    let secrets = parse(puzzle);
+    let mut sequence_value = vec![(0, 0); SEQUENCE_VALUE_TABLE_SIZE];
+    let mut buffer = vec![[0; LANES]; STEPS - 3];
+    let mut chunks = secrets.chunks_exact(LANES);
+    let mut monkey_idx = 1;
+    for chunk in &mut chunks {
+        sales(chunk.try_into().unwrap(), &mut buffer);
+        record(&buffer, monkey_idx, &mut sequence_value);
+        monkey_idx += LANES as u16;
+    }
+    for &secret in chunks.remainder() {
+        unsafe { add_sequence_values_array(secret, monkey_idx, &mut sequence_value) };
+        monkey_idx += 1;
+    }
+    sequence_value.iter().map(|v| v.1).max().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day22::two_array;
+
+    fn buyers(count: u32) -> String {
+        (0..count)
+            .map(|i| (i.wrapping_mul(2654435761) & SECRET_MASK).to_string() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn lanes_match_scalar() {
+        let mut lanes: Lanes = [1, 10, 100, 2024, 123, 0, SECRET_MASK, 15887950];
+        let mut expected = lanes;
+        advance(&mut lanes, 2000);
+        advance_scalar(&mut expected, 2000);
+        assert_eq!(lanes, expected);
+        assert_eq!(lanes[..4].iter().map(|&s| s as u64).sum::<u64>(), 37327623);
+    }
+
+    #[test]
+    fn examples() {
+        assert_eq!(part1(include_str!("test.txt")), 37327623);
+        assert_eq!(part2("1\n2\n3\n2024\n"), 23);
+        let many = "1\n2\n3\n2024\n".repeat(5);
+        assert_eq!(part2(&many), 5 * 23);
+    }
+
+    #[test]
+    fn table_matches_array() {
+        let puzzle = buyers(37);
+        assert_eq!(part2(&puzzle), two_array(&puzzle));
+
+        let secrets = parse(&puzzle);
+        let mut scalar = vec![[0; LANES]; STEPS - 3];
+        let mut vector = scalar.clone();
+        for lanes in secrets.chunks_exact(LANES) {
+            let lanes: Lanes = lanes.try_into().unwrap();
+            sales_scalar(lanes, &mut scalar);
+            sales(lanes, &mut vector);
+            assert_eq!(scalar, vector);
+        }
+    }
+}
@@ -8,6 +8,8 @@ use rustc_hash::FxHashMap;
 
 use crate::util::parse_initial_digits;
 
+pub mod lanes;
+
 const SECRET_MASK: u32 = 16777216 - 1;
 
 #[inline]
@@ -205,6 +207,16 @@ fn two_hashmap_rayon(puzzle: &str) -> u32 {
     sequence_value.iter().map(|v| v.1 .1).max().unwrap()
 }
 
+#[aoc(day22, part1, lanes)]
+fn one_lanes(puzzle: &str) -> u64 {
+    lanes::part1(puzzle)
+}
+
+#[aoc(day22, part2, lanes)]
+fn two_lanes(puzzle: &str) -> u16 {
+    lanes::part2(puzzle)
+}
+
 pub fn part2(puzzle: &str) -> u16 {
     two_array(puzzle)
 }
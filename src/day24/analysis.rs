@@ -0,0 +1,251 @@
+use rustc_hash::FxHashMap;
+
+use super::{circuit::Netlist, numbered_wire, WireName, WireValue};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMismatch {
+    pub wire: WireName,
+    pub missing: Vec<WireName>,
+    pub unexpected: Vec<WireName>,
+}
+
+/// Structural queries over a netlist. Wires which are referenced but never driven count as
+/// primary inputs, wires on or behind a cycle have no depth.
+pub struct NetlistAnalysis {
+    names: Vec<WireName>,
+    indices: FxHashMap<WireName, usize>,
+    operands: Vec<Option<[usize; 2]>>,
+    fan_out: Vec<Vec<usize>>,
+    depths: Vec<Option<u32>>,
+}
+
+impl NetlistAnalysis {
+    pub fn new(netlist: &Netlist) -> Self {
+        let mut names: Vec<WireName> = netlist.keys().copied().collect();
+        names.sort_unstable();
+        let mut indices: FxHashMap<WireName, usize> =
+            names.iter().enumerate().map(|(i, &w)| (w, i)).collect();
+        let mut operands = vec![None; names.len()];
+        for i in 0..names.len() {
+            if let WireValue::GateAnd(a, b) | WireValue::GateOr(a, b) | WireValue::GateXor(a, b) =
+                &netlist[&names[i]]
+            {
+                let mut index_of = |wire: &WireName| {
+                    *indices.entry(*wire).or_insert_with(|| {
+                        names.push(*wire);
+                        names.len() - 1
+                    })
+                };
+                operands[i] = Some([index_of(a), index_of(b)]);
+            }
+        }
+        operands.resize(names.len(), None);
+
+        let mut fan_out = vec![Vec::new(); names.len()];
+        let mut pending = vec![0u8; names.len()];
+        for (i, ops) in operands.iter().enumerate() {
+            for &op in ops.iter().flatten() {
+                fan_out[op].push(i);
+                pending[i] += 1;
+            }
+        }
+
+        let mut depths = vec![None; names.len()];
+        let mut ready: Vec<usize> = (0..names.len()).filter(|&i| pending[i] == 0).collect();
+        while let Some(i) = ready.pop() {
+            depths[i] = Some(match operands[i] {
+                Some([a, b]) => depths[a].max(depths[b]).unwrap() + 1,
+                None => 0,
+            });
+            for &succ in &fan_out[i] {
+                pending[succ] -= 1;
+                if pending[succ] == 0 {
+                    ready.push(succ);
+                }
+            }
+        }
+
+        Self {
+            names,
+            indices,
+            operands,
+            fan_out,
+            depths,
+        }
+    }
+
+    fn cone(&self, wire: &WireName, next: impl Fn(usize) -> Vec<usize>) -> Vec<WireName> {
+        let Some(&start) = self.indices.get(wire) else {
+            return Vec::new();
+        };
+        let mut seen = vec![false; self.names.len()];
+        let mut stack = vec![start];
+        seen[start] = true;
+        let mut cone = Vec::new();
+        while let Some(i) = stack.pop() {
+            for n in next(i) {
+                if !seen[n] {
+                    seen[n] = true;
+                    cone.push(self.names[n]);
+                    stack.push(n);
+                }
+            }
+        }
+        cone.sort_unstable();
+        cone
+    }
+
+    /// All wires `wire` transitively depends on, sorted.
+    pub fn fan_in(&self, wire: &WireName) -> Vec<WireName> {
+        self.cone(wire, |i| self.operands[i].map_or(Vec::new(), Vec::from))
+    }
+
+    /// All wires transitively depending on `wire`, sorted.
+    pub fn fan_out(&self, wire: &WireName) -> Vec<WireName> {
+        self.cone(wire, |i| self.fan_out[i].clone())
+    }
+
+    pub fn controlling_inputs(&self, wire: &WireName) -> Vec<WireName> {
+        let mut inputs = self.fan_in(wire);
+        inputs.retain(|w| self.operands[self.indices[w]].is_none());
+        inputs
+    }
+
+    /// Logic levels between the primary inputs and `wire`.
+    pub fn depth(&self, wire: &WireName) -> Option<u32> {
+        self.depths[*self.indices.get(wire)?]
+    }
+
+    pub fn output_depths(&self) -> Vec<(WireName, Option<u32>)> {
+        (0..100)
+            .map(|i| numbered_wire(b'z', i))
+            .take_while(|z| self.indices.contains_key(z))
+            .map(|z| (z, self.depth(&z)))
+            .collect()
+    }
+
+    pub fn critical_path_length(&self) -> u32 {
+        self.depths.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// The longest chain of gates, starting at a primary input. Wires without a depth are never
+    /// part of it, so a netlist made only of cycles has an empty critical path.
+    pub fn critical_path(&self) -> Vec<WireName> {
+        let Some(mut current) = (0..self.names.len())
+            .filter(|&i| self.depths[i].is_some())
+            .max_by_key(|&i| self.depths[i])
+        else {
+            return Vec::new();
+        };
+        let mut path = vec![self.names[current]];
+        while let Some([a, b]) = self.operands[current] {
+            current = if self.depths[a] >= self.depths[b] {
+                a
+            } else {
+                b
+            };
+            path.push(self.names[current]);
+        }
+        path.reverse();
+        path
+    }
+
+    pub fn check_inputs(&self, wire: &WireName, expected: &[WireName]) -> Option<InputMismatch> {
+        let inputs = self.controlling_inputs(wire);
+        let missing: Vec<_> = expected
+            .iter()
+            .filter(|w| !inputs.contains(w))
+            .copied()
+            .collect();
+        let unexpected: Vec<_> = inputs
+            .into_iter()
+            .filter(|w| !expected.contains(w))
+            .collect();
+        if missing.is_empty() && unexpected.is_empty() {
+            None
+        } else {
+            Some(InputMismatch {
+                wire: *wire,
+                missing,
+                unexpected,
+            })
+        }
+    }
+
+    /// Checks that each output bit of an adder depends on exactly the input bits at or below it.
+    pub fn adder_input_mismatches(&self) -> Vec<InputMismatch> {
+        let bits = (0..100)
+            .take_while(|&i| self.indices.contains_key(&numbered_wire(b'x', i)))
+            .count() as i8;
+        let mut expected = Vec::new();
+        self.output_depths()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(z, (z_wire, _))| {
+                let z = z as i8;
+                if z < bits {
+                    expected.extend([numbered_wire(b'x', z), numbered_wire(b'y', z)]);
+                    expected.sort_unstable();
+                }
+                self.check_inputs(&z_wire, &expected)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day24::{circuit::CircuitKind, parse_netlist};
+
+    #[test]
+    fn ripple_carry_adder() {
+        let netlist = CircuitKind::RippleCarryAdder.reference(4);
+        let analysis = NetlistAnalysis::new(&netlist);
+        assert_eq!(
+            analysis.controlling_inputs(b"z01"),
+            vec![*b"x00", *b"x01", *b"y00", *b"y01"]
+        );
+        assert!(analysis.fan_out(b"x03").contains(b"z04"));
+        assert!(!analysis.fan_out(b"x03").contains(b"z02"));
+        assert_eq!(analysis.depth(b"z00"), Some(1));
+        assert_eq!(analysis.critical_path_length(), 7);
+        assert_eq!(analysis.critical_path().len(), 8);
+        assert_eq!(analysis.adder_input_mismatches(), vec![]);
+    }
+
+    #[test]
+    fn swapped_outputs() {
+        let mut netlist = CircuitKind::RippleCarryAdder.reference(4);
+        let z01 = netlist.remove(b"z01").unwrap();
+        let z02 = netlist.insert(*b"z02", z01).unwrap();
+        netlist.insert(*b"z01", z02);
+        let analysis = NetlistAnalysis::new(&netlist);
+        let mismatches = analysis.adder_input_mismatches();
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[0].wire, *b"z01");
+        assert_eq!(mismatches[0].unexpected, vec![*b"x02", *b"y02"]);
+        assert_eq!(mismatches[1].missing, vec![*b"x02", *b"y02"]);
+    }
+
+    #[test]
+    fn example() {
+        let analysis = NetlistAnalysis::new(&parse_netlist(include_str!("test-small.txt")));
+        assert_eq!(analysis.controlling_inputs(b"z02"), vec![*b"x02", *b"y02"]);
+        assert_eq!(analysis.output_depths().len(), 3);
+        assert_eq!(analysis.critical_path_length(), 1);
+    }
+
+    #[test]
+    fn cyclic() {
+        let netlist = Netlist::from_iter([
+            (*b"aaa", WireValue::GateAnd(*b"bbb", *b"ccc")),
+            (*b"bbb", WireValue::GateOr(*b"aaa", *b"ccc")),
+            (*b"ccc", WireValue::GateXor(*b"aaa", *b"bbb")),
+        ]);
+        let analysis = NetlistAnalysis::new(&netlist);
+        assert_eq!(analysis.depth(b"aaa"), None);
+        assert_eq!(analysis.critical_path_length(), 0);
+        assert_eq!(analysis.critical_path(), Vec::<WireName>::new());
+    }
+}
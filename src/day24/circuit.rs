@@ -0,0 +1,647 @@
+use rustc_hash::FxHashMap;
+
+use super::{numbered_wire, WireName, WireValue};
+
+pub type Netlist = FxHashMap<WireName, WireValue>;
+
+const ONE: WireName = *b"one";
+const LANES: usize = 64;
+const BLOCK_SIZE: u8 = 4;
+const RANDOM_VECTORS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Add,
+    /// Two's complement difference, the top output bit is the borrow.
+    Subtract,
+    Multiply,
+}
+
+impl Operation {
+    pub fn output_bits(self, bits: u8) -> u8 {
+        match self {
+            Operation::Add | Operation::Subtract => bits + 1,
+            Operation::Multiply => 2 * bits,
+        }
+    }
+
+    pub fn apply(self, x: u128, y: u128, bits: u8) -> u128 {
+        let res = match self {
+            Operation::Add => x + y,
+            Operation::Subtract => x.wrapping_sub(y),
+            Operation::Multiply => x * y,
+        };
+        res & mask(self.output_bits(bits))
+    }
+
+    fn max_bits(self) -> u8 {
+        match self {
+            Operation::Add | Operation::Subtract => 99,
+            Operation::Multiply => 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CircuitKind {
+    RippleCarryAdder,
+    CarryLookaheadAdder,
+    CarrySelectAdder,
+    RippleBorrowSubtractor,
+    ArrayMultiplier,
+}
+
+impl CircuitKind {
+    pub const ALL: [CircuitKind; 5] = [
+        CircuitKind::RippleCarryAdder,
+        CircuitKind::CarryLookaheadAdder,
+        CircuitKind::CarrySelectAdder,
+        CircuitKind::RippleBorrowSubtractor,
+        CircuitKind::ArrayMultiplier,
+    ];
+
+    pub fn operation(self) -> Operation {
+        match self {
+            CircuitKind::RippleCarryAdder
+            | CircuitKind::CarryLookaheadAdder
+            | CircuitKind::CarrySelectAdder => Operation::Add,
+            CircuitKind::RippleBorrowSubtractor => Operation::Subtract,
+            CircuitKind::ArrayMultiplier => Operation::Multiply,
+        }
+    }
+
+    /// Builds the textbook circuit of this kind for `bits`-wide x and y inputs.
+    pub fn reference(self, bits: u8) -> Netlist {
+        assert!(
+            (1..=self.operation().max_bits()).contains(&bits),
+            "{self:?} cannot be built with {bits} bits"
+        );
+        let mut builder = NetlistBuilder::new(bits);
+        match self {
+            CircuitKind::RippleCarryAdder => builder.ripple_carry_adder(),
+            CircuitKind::CarryLookaheadAdder => builder.carry_lookahead_adder(),
+            CircuitKind::CarrySelectAdder => builder.carry_select_adder(),
+            CircuitKind::RippleBorrowSubtractor => builder.ripple_borrow_subtractor(),
+            CircuitKind::ArrayMultiplier => builder.array_multiplier(),
+        }
+        builder.finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recognition {
+    pub kind: CircuitKind,
+    pub bits: u8,
+    /// Output bit positions that differ from the reference model for some input.
+    pub deviating_bits: Vec<u8>,
+}
+
+impl Recognition {
+    pub fn is_correct(&self) -> bool {
+        self.deviating_bits.is_empty()
+    }
+}
+
+/// Finds the circuit kind which best explains `netlist`: functionally first, then by gate counts.
+pub fn recognise(netlist: &Netlist) -> Option<Recognition> {
+    let bits = input_bits(netlist, b'x');
+    if bits == 0 || bits != input_bits(netlist, b'y') {
+        return None;
+    }
+    let output_bits = input_bits(netlist, b'z');
+    let gates = gate_counts(netlist);
+
+    CircuitKind::ALL
+        .into_iter()
+        .filter(|kind| {
+            let operation = kind.operation();
+            bits <= operation.max_bits() && operation.output_bits(bits) == output_bits
+        })
+        .map(|kind| {
+            let reference = kind.reference(bits);
+            let deviating_bits = deviations(netlist, &reference, kind.operation(), bits);
+            let structural_distance = gate_counts(&reference)
+                .iter()
+                .zip(gates)
+                .map(|(a, b)| a.abs_diff(b))
+                .sum::<usize>();
+            (
+                (deviating_bits.len(), structural_distance),
+                Recognition {
+                    kind,
+                    bits,
+                    deviating_bits,
+                },
+            )
+        })
+        .min_by_key(|(score, _)| *score)
+        .map(|(_, recognition)| recognition)
+}
+
+/// Returns the output bit positions in which `netlist` deviates from a `kind` circuit.
+pub fn check(netlist: &Netlist, kind: CircuitKind, bits: u8) -> Vec<u8> {
+    deviations(netlist, &kind.reference(bits), kind.operation(), bits)
+}
+
+fn deviations(netlist: &Netlist, reference: &Netlist, operation: Operation, bits: u8) -> Vec<u8> {
+    let output_bits = operation.output_bits(bits);
+    let mut deviating = 0u128;
+    for chunk in test_vectors(bits).chunks(LANES) {
+        let mut inputs = FxHashMap::default();
+        for bit in 0..bits {
+            let (mut x, mut y) = (0u64, 0u64);
+            for (lane, (x_val, y_val)) in chunk.iter().enumerate() {
+                x |= (((x_val >> bit) & 1) as u64) << lane;
+                y |= (((y_val >> bit) & 1) as u64) << lane;
+            }
+            inputs.insert(numbered_wire(b'x', bit as i8), x);
+            inputs.insert(numbered_wire(b'y', bit as i8), y);
+        }
+        let mut actual = Simulation::new(netlist, &inputs);
+        let mut expected = Simulation::new(reference, &inputs);
+        for bit in 0..output_bits {
+            let z_wire = numbered_wire(b'z', bit as i8);
+            let expected = expected
+                .signal(z_wire)
+                .expect("reference circuit should be acyclic");
+            if actual.signal(z_wire) != Some(expected) {
+                deviating |= 1 << bit;
+            }
+        }
+    }
+    (0..output_bits)
+        .filter(|bit| deviating & (1 << bit) != 0)
+        .collect()
+}
+
+fn test_vectors(bits: u8) -> Vec<(u128, u128)> {
+    let max = mask(bits);
+    let mut vectors = vec![(0, 0), (max, max), (max, 1), (1, max), (max, 0), (0, max)];
+    for bit in 0..bits {
+        let single = 1 << bit;
+        vectors.extend([(single, single), (single, 0), (0, single), (max, single)]);
+    }
+    let mut state: u64 = 0x2024_1224_dead_beef;
+    let mut next_random = || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_f491_4f6c_dd1d) as u128
+    };
+    for _ in 0..RANDOM_VECTORS {
+        let x = (next_random() << 64 | next_random()) & max;
+        let y = (next_random() << 64 | next_random()) & max;
+        vectors.push((x, y));
+    }
+    vectors
+}
+
+#[inline]
+fn mask(bits: u8) -> u128 {
+    if bits >= 128 {
+        u128::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+fn input_bits(netlist: &Netlist, prefix: u8) -> u8 {
+    (0..100)
+        .take_while(|&i| netlist.contains_key(&numbered_wire(prefix, i)))
+        .count() as u8
+}
+
+fn gate_counts(netlist: &Netlist) -> [usize; 3] {
+    let mut counts = [0; 3];
+    for value in netlist.values() {
+        match value {
+            WireValue::Determined(_) => {}
+            WireValue::GateAnd(_, _) => counts[0] += 1,
+            WireValue::GateOr(_, _) => counts[1] += 1,
+            WireValue::GateXor(_, _) => counts[2] += 1,
+        }
+    }
+    counts
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SignalState {
+    Visiting,
+    Done(Option<u64>),
+}
+
+/// Evaluates 64 input vectors at once, one per bit lane. Wires which are missing or sit on a
+/// cycle have no signal.
+struct Simulation<'n> {
+    netlist: &'n Netlist,
+    inputs: &'n FxHashMap<WireName, u64>,
+    states: FxHashMap<WireName, SignalState>,
+}
+
+impl<'n> Simulation<'n> {
+    fn new(netlist: &'n Netlist, inputs: &'n FxHashMap<WireName, u64>) -> Self {
+        Self {
+            netlist,
+            inputs,
+            states: FxHashMap::default(),
+        }
+    }
+
+    fn done(&self, wire: &WireName) -> Option<u64> {
+        match self.states.get(wire) {
+            Some(SignalState::Done(signal)) => *signal,
+            _ => None,
+        }
+    }
+
+    fn signal(&mut self, wire: WireName) -> Option<u64> {
+        let mut stack = vec![(wire, false)];
+        while let Some((wire, expanded)) = stack.pop() {
+            if expanded {
+                if let Some(SignalState::Done(_)) = self.states.get(&wire) {
+                    // Already resolved as part of a cycle.
+                    continue;
+                }
+                let signal = match self.netlist[&wire] {
+                    WireValue::GateAnd(a, b) => {
+                        self.done(&a).zip(self.done(&b)).map(|(a, b)| a & b)
+                    }
+                    WireValue::GateOr(a, b) => self.done(&a).zip(self.done(&b)).map(|(a, b)| a | b),
+                    WireValue::GateXor(a, b) => {
+                        self.done(&a).zip(self.done(&b)).map(|(a, b)| a ^ b)
+                    }
+                    WireValue::Determined(_) => unreachable!(),
+                };
+                self.states.insert(wire, SignalState::Done(signal));
+                continue;
+            }
+            match self.states.get(&wire) {
+                Some(SignalState::Done(_)) => continue,
+                Some(SignalState::Visiting) => {
+                    self.states.insert(wire, SignalState::Done(None));
+                    continue;
+                }
+                None => {}
+            }
+            match self.netlist.get(&wire) {
+                None => {
+                    self.states.insert(wire, SignalState::Done(None));
+                }
+                Some(WireValue::Determined(value)) => {
+                    let signal = match self.inputs.get(&wire) {
+                        Some(signal) => *signal,
+                        None if *value => u64::MAX,
+                        None => 0,
+                    };
+                    self.states.insert(wire, SignalState::Done(Some(signal)));
+                }
+                Some(
+                    WireValue::GateAnd(a, b) | WireValue::GateOr(a, b) | WireValue::GateXor(a, b),
+                ) => {
+                    self.states.insert(wire, SignalState::Visiting);
+                    stack.extend([(wire, true), (*a, false), (*b, false)]);
+                }
+            }
+        }
+        self.done(&wire)
+    }
+}
+
+struct NetlistBuilder {
+    bits: u8,
+    wires: Netlist,
+    outputs: FxHashMap<WireName, WireName>,
+    next_gate: usize,
+}
+
+impl NetlistBuilder {
+    fn new(bits: u8) -> Self {
+        let mut wires = FxHashMap::default();
+        for bit in 0..bits {
+            wires.insert(x(bit), WireValue::Determined(false));
+            wires.insert(y(bit), WireValue::Determined(false));
+        }
+        wires.insert(ONE, WireValue::Determined(true));
+        Self {
+            bits,
+            wires,
+            outputs: FxHashMap::default(),
+            next_gate: 0,
+        }
+    }
+
+    fn gate(
+        &mut self,
+        a: WireName,
+        b: WireName,
+        gate: fn(WireName, WireName) -> WireValue,
+    ) -> WireName {
+        // Internal wires are upper case so they can never clash with puzzle wire names.
+        let n = self.next_gate;
+        assert!(n < 26 * 26 * 26, "ran out of internal wire names");
+        self.next_gate += 1;
+        let name = [
+            b'A' + (n / (26 * 26)) as u8,
+            b'A' + (n / 26 % 26) as u8,
+            b'A' + (n % 26) as u8,
+        ];
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        self.wires.insert(name, gate(a, b));
+        name
+    }
+
+    fn and(&mut self, a: WireName, b: WireName) -> WireName {
+        self.gate(a, b, WireValue::GateAnd)
+    }
+
+    fn or(&mut self, a: WireName, b: WireName) -> WireName {
+        self.gate(a, b, WireValue::GateOr)
+    }
+
+    fn xor(&mut self, a: WireName, b: WireName) -> WireName {
+        self.gate(a, b, WireValue::GateXor)
+    }
+
+    fn not(&mut self, a: WireName) -> WireName {
+        self.xor(a, ONE)
+    }
+
+    fn mux(&mut self, select: WireName, when_set: WireName, when_clear: WireName) -> WireName {
+        let set = self.and(select, when_set);
+        let not_select = self.not(select);
+        let clear = self.and(not_select, when_clear);
+        self.or(set, clear)
+    }
+
+    fn half_adder(&mut self, a: WireName, b: WireName) -> (WireName, WireName) {
+        (self.xor(a, b), self.and(a, b))
+    }
+
+    fn full_adder(&mut self, a: WireName, b: WireName, carry: WireName) -> (WireName, WireName) {
+        let (partial, carry_1) = self.half_adder(a, b);
+        let (sum, carry_2) = self.half_adder(partial, carry);
+        (sum, self.or(carry_1, carry_2))
+    }
+
+    fn add_bits(&mut self, operands: &[WireName]) -> (WireName, Option<WireName>) {
+        match *operands {
+            [a] => (a, None),
+            [a, b] => {
+                let (sum, carry) = self.half_adder(a, b);
+                (sum, Some(carry))
+            }
+            [a, b, c] => {
+                let (sum, carry) = self.full_adder(a, b, c);
+                (sum, Some(carry))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn output(&mut self, bit: u8, wire: WireName) {
+        self.outputs.insert(wire, numbered_wire(b'z', bit as i8));
+    }
+
+    fn finish(self) -> Netlist {
+        let rename = |wire: WireName| self.outputs.get(&wire).copied().unwrap_or(wire);
+        let sorted = |a: WireName, b: WireName| if a < b { (a, b) } else { (b, a) };
+        self.wires
+            .iter()
+            .map(|(&wire, value)| {
+                let value = match *value {
+                    WireValue::Determined(value) => WireValue::Determined(value),
+                    WireValue::GateAnd(a, b) => {
+                        let (a, b) = sorted(rename(a), rename(b));
+                        WireValue::GateAnd(a, b)
+                    }
+                    WireValue::GateOr(a, b) => {
+                        let (a, b) = sorted(rename(a), rename(b));
+                        WireValue::GateOr(a, b)
+                    }
+                    WireValue::GateXor(a, b) => {
+                        let (a, b) = sorted(rename(a), rename(b));
+                        WireValue::GateXor(a, b)
+                    }
+                };
+                (rename(wire), value)
+            })
+            .collect()
+    }
+
+    fn ripple_carry_adder(&mut self) {
+        let (sum, mut carry) = self.half_adder(x(0), y(0));
+        self.output(0, sum);
+        for bit in 1..self.bits {
+            let sum;
+            (sum, carry) = self.full_adder(x(bit), y(bit), carry);
+            self.output(bit, sum);
+        }
+        self.output(self.bits, carry);
+    }
+
+    fn carry_lookahead_adder(&mut self) {
+        let (propagate, generate): (Vec<_>, Vec<_>) = (0..self.bits)
+            .map(|bit| self.half_adder(x(bit), y(bit)))
+            .unzip();
+        let mut carry_in: Option<WireName> = None;
+        for start in (0..self.bits).step_by(BLOCK_SIZE as usize) {
+            let end = (start + BLOCK_SIZE).min(self.bits);
+            for bit in start..end {
+                let sum = match carry_in_at(self, &propagate, &generate, carry_in, start, bit) {
+                    Some(carry) => self.xor(propagate[bit as usize], carry),
+                    None => propagate[bit as usize],
+                };
+                self.output(bit, sum);
+            }
+            carry_in = carry_in_at(self, &propagate, &generate, carry_in, start, end);
+        }
+        self.output(self.bits, carry_in.unwrap());
+
+        // Two-level expansion of the carry into `bit` from the block carry at `start`.
+        fn carry_in_at(
+            builder: &mut NetlistBuilder,
+            propagate: &[WireName],
+            generate: &[WireName],
+            block_carry: Option<WireName>,
+            start: u8,
+            bit: u8,
+        ) -> Option<WireName> {
+            let mut terms = Vec::new();
+            for source in start..bit {
+                let mut term = generate[source as usize];
+                for p in source + 1..bit {
+                    term = builder.and(term, propagate[p as usize]);
+                }
+                terms.push(term);
+            }
+            if let Some(mut term) = block_carry {
+                for p in start..bit {
+                    term = builder.and(term, propagate[p as usize]);
+                }
+                terms.push(term);
+            }
+            terms.into_iter().reduce(|a, b| builder.or(a, b))
+        }
+    }
+
+    fn carry_select_adder(&mut self) {
+        let first_end = BLOCK_SIZE.min(self.bits);
+        let (sum, mut carry) = self.half_adder(x(0), y(0));
+        self.output(0, sum);
+        for bit in 1..first_end {
+            let sum;
+            (sum, carry) = self.full_adder(x(bit), y(bit), carry);
+            self.output(bit, sum);
+        }
+        for start in (first_end..self.bits).step_by(BLOCK_SIZE as usize) {
+            let end = (start + BLOCK_SIZE).min(self.bits);
+            let (mut carry_0, mut carry_1) = (None, ONE);
+            let mut sums = Vec::new();
+            for bit in start..end {
+                let (sum_0, next_0) = match carry_0 {
+                    Some(c) => self.full_adder(x(bit), y(bit), c),
+                    None => self.half_adder(x(bit), y(bit)),
+                };
+                let (sum_1, next_1) = self.full_adder(x(bit), y(bit), carry_1);
+                sums.push((bit, sum_0, sum_1));
+                (carry_0, carry_1) = (Some(next_0), next_1);
+            }
+            for (bit, sum_0, sum_1) in sums {
+                let sum = self.mux(carry, sum_1, sum_0);
+                self.output(bit, sum);
+            }
+            let selected_1 = self.and(carry, carry_1);
+            carry = self.or(carry_0.unwrap(), selected_1);
+        }
+        self.output(self.bits, carry);
+    }
+
+    fn ripple_borrow_subtractor(&mut self) {
+        let mut borrow: Option<WireName> = None;
+        for bit in 0..self.bits {
+            let difference = self.xor(x(bit), y(bit));
+            let not_x = self.not(x(bit));
+            let borrow_out = self.and(not_x, y(bit));
+            match borrow {
+                None => {
+                    self.output(bit, difference);
+                    borrow = Some(borrow_out);
+                }
+                Some(b) => {
+                    let sum = self.xor(difference, b);
+                    self.output(bit, sum);
+                    let not_difference = self.not(difference);
+                    let borrow_through = self.and(not_difference, b);
+                    borrow = Some(self.or(borrow_out, borrow_through));
+                }
+            }
+        }
+        self.output(self.bits, borrow.unwrap());
+    }
+
+    fn array_multiplier(&mut self) {
+        let bits = self.bits;
+        let first_row: Vec<_> = (0..bits).map(|j| self.and(x(j), y(0))).collect();
+        self.output(0, first_row[0]);
+        // `upper[k]` has weight `row + k`, missing wires are zero.
+        let mut upper: Vec<WireName> = first_row[1..].to_vec();
+        for row in 1..bits {
+            let mut carry = None;
+            let mut next = Vec::with_capacity(bits as usize);
+            for j in 0..bits {
+                let product = self.and(x(j), y(row));
+                let operands: Vec<_> = [Some(product), upper.get(j as usize).copied(), carry]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                let sum;
+                (sum, carry) = self.add_bits(&operands);
+                next.push(sum);
+            }
+            next.extend(carry);
+            self.output(row, next[0]);
+            upper = next.split_off(1);
+        }
+        if bits == 1 {
+            let zero = self.xor(x(0), x(0));
+            upper.push(zero);
+        }
+        for (k, wire) in upper.into_iter().enumerate() {
+            self.output(bits + k as u8, wire);
+        }
+    }
+}
+
+#[inline]
+fn x(bit: u8) -> WireName {
+    numbered_wire(b'x', bit as i8)
+}
+
+#[inline]
+fn y(bit: u8) -> WireName {
+    numbered_wire(b'y', bit as i8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(netlist: &Netlist, operation: Operation, bits: u8) {
+        for (x, y) in test_vectors(bits) {
+            let mut inputs = FxHashMap::default();
+            for bit in 0..bits {
+                inputs.insert(numbered_wire(b'x', bit as i8), ((x >> bit) & 1) as u64);
+                inputs.insert(numbered_wire(b'y', bit as i8), ((y >> bit) & 1) as u64);
+            }
+            let mut simulation = Simulation::new(netlist, &inputs);
+            let z = (0..operation.output_bits(bits)).fold(0u128, |z, bit| {
+                let signal = simulation.signal(numbered_wire(b'z', bit as i8)).unwrap();
+                z | ((signal & 1) as u128) << bit
+            });
+            assert_eq!(z, operation.apply(x, y, bits), "{operation:?} {x} {y}");
+        }
+    }
+
+    #[test]
+    fn references_compute_their_operation() {
+        for kind in CircuitKind::ALL {
+            for bits in [1, 2, 5, 9] {
+                evaluate(&kind.reference(bits), kind.operation(), bits);
+            }
+        }
+    }
+
+    #[test]
+    fn recognises_references() {
+        for kind in CircuitKind::ALL {
+            let recognition = recognise(&kind.reference(12)).unwrap();
+            assert_eq!(recognition.kind, kind);
+            assert_eq!(recognition.bits, 12);
+            assert!(recognition.is_correct());
+        }
+    }
+
+    #[test]
+    fn reports_swapped_outputs() {
+        let mut netlist = CircuitKind::RippleCarryAdder.reference(8);
+        let z03 = netlist.remove(b"z03").unwrap();
+        let z05 = netlist.insert(*b"z05", z03).unwrap();
+        netlist.insert(*b"z03", z05);
+        assert_eq!(
+            check(&netlist, CircuitKind::RippleCarryAdder, 8),
+            vec![3, 5]
+        );
+        let recognition = recognise(&netlist).unwrap();
+        assert_eq!(recognition.kind, CircuitKind::RippleCarryAdder);
+        assert_eq!(recognition.deviating_bits, vec![3, 5]);
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let mut netlist = CircuitKind::RippleCarryAdder.reference(4);
+        // Make bit 2 depend on itself.
+        let z02 = netlist.get_mut(b"z02").unwrap();
+        *z02 = WireValue::GateXor(*b"x02", *b"z02");
+        assert_eq!(check(&netlist, CircuitKind::RippleCarryAdder, 4), vec![2]);
+    }
+}
@@ -4,9 +4,12 @@ use aoc_runner_derive::aoc;
 use itertools::Itertools;
 use rustc_hash::{FxHashMap, FxHashSet};
 
-type WireName = [u8; 3];
+pub mod analysis;
+pub mod circuit;
+
+pub type WireName = [u8; 3];
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum WireValue {
+pub enum WireValue {
     Determined(bool),
     GateAnd(WireName, WireName),
     GateOr(WireName, WireName),
@@ -56,6 +59,18 @@ fn parse(input: &str) -> (FxHashMap<WireName, RefCell<WireValue>>, i8) {
     (wires, z_index)
 }
 
+fn parse_netlist(input: &str) -> circuit::Netlist {
+    let (wires, _) = parse(input);
+    wires
+        .into_iter()
+        .map(|(wire, value)| (wire, value.into_inner()))
+        .collect()
+}
+
+pub fn recognise_circuit(input: &str) -> Option<circuit::Recognition> {
+    circuit::recognise(&parse_netlist(input))
+}
+
 #[inline]
 fn numbered_wire(prefix: u8, index: i8) -> WireName {
     [prefix, b'0' + (index / 10) as u8, b'0' + (index % 10) as u8]
@@ -114,17 +129,16 @@ fn determine_wire_value(
 #[aoc(day24, part2)]
 pub fn part2(input: &str) -> String {
     let (wires, z_max) = parse(input);
-    // let mut expected_inputs = FxHashSet::default();
+    #[cfg(debug_assertions)]
+    for mismatch in analysis::NetlistAnalysis::new(&parse_netlist(input)).adder_input_mismatches() {
+        eprintln!(
+            "Expected {:?} to depend on {:?} and not on {:?}",
+            mismatch.wire, mismatch.missing, mismatch.unexpected
+        );
+    }
     let mut wrong_gates = FxHashSet::default();
     for z in 0..z_max {
         let z_wire = numbered_wire(b'z', z);
-        // expected_inputs.insert(format!("x{:02}", z));
-        // expected_inputs.insert(format!("y{:02}", z));
-        // let inputs = controlling_inputs(&wires, &z_wire);
-        // if inputs != expected_inputs {
-        //     eprintln!("Expected {expected_inputs:?} for {z_wire}, got {inputs:?}");
-        // }
-
         let z_gate = wires.get(&z_wire).unwrap().borrow();
         if !matches!(z_gate.deref(), WireValue::GateXor(_, _)) {
             #[cfg(debug_assertions)]
@@ -316,22 +330,6 @@ pub fn part2(input: &str) -> String {
     out
 }
 
-fn controlling_inputs(
-    wires: &FxHashMap<WireName, RefCell<WireValue>>,
-    wire_name: &WireName,
-) -> FxHashSet<WireName> {
-    match wires.get(wire_name).unwrap().borrow().deref() {
-        WireValue::GateAnd(a, b) | WireValue::GateOr(a, b) | WireValue::GateXor(a, b) => {
-            let a_inputs = controlling_inputs(wires, a);
-            let b_inputs = controlling_inputs(wires, b);
-            let mut inputs = a_inputs;
-            inputs.extend(b_inputs);
-            inputs
-        }
-        WireValue::Determined(_) => FxHashSet::from_iter([*wire_name]),
-    }
-}
-
 #[cfg(test)]
 mod examples {
     use super::*;
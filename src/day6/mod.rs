@@ -1,11 +1,17 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use rustc_hash::{FxBuildHasher, FxHashSet};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
 use std::hash::{Hash, Hasher};
 
+use crate::util::{find_cycle, Direction, RayGrid};
+
+pub mod patrol;
+
 type Output = usize;
-type Coord = u16;
+pub type Coord = u16;
+/// Column and row, counted from 1.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Point(Coord, Coord);
+pub struct Point(pub Coord, pub Coord);
 
 impl Point {
     fn in_range(&self, width: Coord, height: Coord) -> bool {
@@ -20,7 +26,7 @@ impl Hash for Point {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Facing {
+pub enum Facing {
     North,
     South,
     West,
@@ -28,6 +34,15 @@ enum Facing {
 }
 
 impl Facing {
+    fn direction(self) -> Direction {
+        match self {
+            Facing::North => Direction::North,
+            Facing::South => Direction::South,
+            Facing::West => Direction::West,
+            Facing::East => Direction::East,
+        }
+    }
+
     fn step(self, point: Point) -> Point {
         match self {
             Facing::North => Point(point.0, point.1 - 1),
@@ -39,7 +54,7 @@ impl Facing {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Guard(Point, Facing);
+pub struct Guard(pub Point, pub Facing);
 
 impl Guard {
     fn turn_right(&mut self) {
@@ -301,6 +316,187 @@ fn cast_loop(edge_map: &EdgeMap, mut guard: Guard, width: Coord, height: Coord)
     unreachable!()
 }
 
+/// Every cell the guard enters, in order, with the guard state just before first entering it.
+fn trace_first_visits(
+    obstacles: &FxHashSet<Point>,
+    mut guard: Guard,
+    width: Coord,
+    height: Coord,
+) -> Vec<(Point, Guard)> {
+    let mut visited = vec![false; width as usize * height as usize];
+    visited[guard.0 .1 as usize * width as usize + guard.0 .0 as usize] = true;
+    let mut first_visits = Vec::new();
+    while guard.0.in_range(width, height) {
+        let step_pos = guard.1.step(guard.0);
+        if obstacles.contains(&step_pos) {
+            guard.turn_right();
+            continue;
+        }
+        if step_pos.in_range(width, height) {
+            let index = step_pos.1 as usize * width as usize + step_pos.0 as usize;
+            if !visited[index] {
+                visited[index] = true;
+                first_visits.push((step_pos, guard));
+            }
+        }
+        guard.0 = step_pos;
+    }
+    first_visits
+}
+
+/// An [`EdgeMap`] with one additional obstacle, without copying the map.
+struct EdgeMapOverlay<'m> {
+    edge_map: &'m EdgeMap,
+    extra: Point,
+}
+
+impl EdgeMapOverlay<'_> {
+    #[inline(always)]
+    fn cast(&self, guard: Guard) -> Option<Point> {
+        let (pos, extra) = (guard.0, self.extra);
+        let extra_stop = match guard.1 {
+            Facing::North if extra.0 == pos.0 && extra.1 < pos.1 => Point(pos.0, extra.1 + 1),
+            Facing::South if extra.0 == pos.0 && extra.1 > pos.1 => Point(pos.0, extra.1 - 1),
+            Facing::West if extra.1 == pos.1 && extra.0 < pos.0 => Point(extra.0 + 1, pos.1),
+            Facing::East if extra.1 == pos.1 && extra.0 > pos.0 => Point(extra.0 - 1, pos.1),
+            _ => return self.edge_map.cast(guard),
+        };
+        let Some(stop) = self.edge_map.cast(guard) else {
+            return Some(extra_stop);
+        };
+        let distance = |p: Point| p.0.abs_diff(pos.0) + p.1.abs_diff(pos.1);
+        if distance(extra_stop) < distance(stop) {
+            Some(extra_stop)
+        } else {
+            Some(stop)
+        }
+    }
+
+    fn loops(&self, mut guard: Guard) -> bool {
+        let mut path = FxHashSet::default();
+        while path.insert(guard) {
+            match self.cast(guard) {
+                None => return false,
+                Some(stop_pos) => {
+                    guard.0 = stop_pos;
+                    guard.turn_right();
+                }
+            }
+        }
+        true
+    }
+}
+
+#[aoc(day6, part2, parallel)]
+fn two_parallel((obstacles, guard, width, height): &NaiveInput) -> Output {
+    let first_visits = trace_first_visits(obstacles, *guard, *width, *height);
+    let edge_map = EdgeMap::from_obstacles(obstacles, *width, *height);
+    first_visits
+        .par_iter()
+        .filter(|(candidate, before)| {
+            let overlay = EdgeMapOverlay {
+                edge_map: &edge_map,
+                extra: *candidate,
+            };
+            overlay.loops(*before)
+        })
+        .count()
+}
+
+#[aoc(day6, part2, jump_table)]
+fn two_jump_table((obstacles, guard, width, height): &NaiveInput) -> Output {
+    let first_visits = trace_first_visits(obstacles, *guard, *width, *height);
+    let mut grid = RayGrid::from_obstacles(
+        *width as usize - 1,
+        *height as usize - 1,
+        obstacles
+            .iter()
+            .map(|point| (point.0 as usize - 1, point.1 as usize - 1)),
+    );
+    let mut loops = 0;
+    for (candidate, before) in first_visits {
+        let (x, y) = (candidate.0 as usize - 1, candidate.1 as usize - 1);
+        grid.insert(x, y);
+        let start = (
+            (before.0 .0 as usize - 1, before.0 .1 as usize - 1),
+            before.1.direction(),
+        );
+        let cycle = find_cycle(start, |((x, y), direction)| {
+            Some((grid.cast(x, y, direction)?, direction.turn_right()))
+        });
+        if cycle.is_some() {
+            loops += 1;
+        }
+        grid.remove(x, y);
+    }
+    loops
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopTrace {
+    pub obstruction: Point,
+    /// The first guard state the guard returns to.
+    pub entry: Guard,
+    /// The guard states after each turn around the loop, starting at `entry`.
+    pub cycle: Vec<Guard>,
+    /// Number of steps to walk the loop once.
+    pub length: usize,
+}
+
+fn cast_loop_trace(
+    edge_map: &EdgeMap,
+    mut guard: Guard,
+    width: Coord,
+    height: Coord,
+) -> Option<(Guard, Vec<Guard>)> {
+    let mut path = Vec::new();
+    let mut seen = FxHashMap::default();
+    while guard.0.in_range(width, height) {
+        if let Some(&start) = seen.get(&guard) {
+            return Some((guard, path.split_off(start)));
+        }
+        seen.insert(guard, path.len());
+        path.push(guard);
+        guard.0 = edge_map.cast(guard)?;
+        guard.turn_right();
+    }
+    unreachable!()
+}
+
+/// Every obstruction that makes the guard loop, with the loop it causes, ordered by row.
+pub fn loop_traces(puzzle: &str) -> Vec<LoopTrace> {
+    let (obstacles, guard, width, height) = parse_naive(puzzle);
+    let base_path = trace_base_path(&obstacles, guard, width, height);
+    let mut edge_map = EdgeMap::from_obstacles(&obstacles, width, height);
+    let mut candidates: Vec<Point> = base_path
+        .iter()
+        .map(|state| state.0)
+        .filter(|&point| point != guard.0)
+        .collect();
+    candidates.sort_unstable_by_key(|point| (point.1, point.0));
+    candidates.dedup();
+
+    let mut traces = Vec::new();
+    for obstruction in candidates {
+        edge_map.insert(obstruction);
+        if let Some((entry, cycle)) = cast_loop_trace(&edge_map, guard, width, height) {
+            let length = cycle
+                .iter()
+                .zip(cycle.iter().cycle().skip(1))
+                .map(|(a, b)| (a.0 .0.abs_diff(b.0 .0) + a.0 .1.abs_diff(b.0 .1)) as usize)
+                .sum();
+            traces.push(LoopTrace {
+                obstruction,
+                entry,
+                cycle,
+                length,
+            });
+        }
+        edge_map.remove(&obstruction);
+    }
+    traces
+}
+
 #[aoc(day6, part1, cast)]
 fn one_cast((obstacles, guard, width, height): &NaiveInput) -> Output {
     let edge_map = EdgeMap::from_obstacles(&obstacles, *width, *height);
@@ -341,4 +537,39 @@ mod examples {
         let res = part2(include_str!("test.txt"));
         assert_eq!(res, 6);
     }
+
+    #[test]
+    fn example2_parallel() {
+        let res = two_parallel(&parse_naive(include_str!("test.txt")));
+        assert_eq!(res, 6);
+    }
+
+    #[test]
+    fn example2_jump_table() {
+        let res = two_jump_table(&parse_naive(include_str!("test.txt")));
+        assert_eq!(res, 6);
+    }
+
+    #[test]
+    fn example2_traces() {
+        let traces = loop_traces(include_str!("test.txt"));
+        let obstructions: Vec<Point> = traces.iter().map(|trace| trace.obstruction).collect();
+        assert_eq!(
+            obstructions,
+            vec![
+                Point(4, 7),
+                Point(7, 8),
+                Point(8, 8),
+                Point(2, 9),
+                Point(4, 9),
+                Point(8, 10)
+            ]
+        );
+        // Blocking next to the start sends the guard round the central rectangle.
+        let trace = &traces[0];
+        assert_eq!(trace.entry, trace.cycle[0]);
+        assert_eq!(trace.cycle.len(), 4);
+        assert!(trace.cycle.contains(&Guard(Point(5, 2), Facing::East)));
+        assert!(trace.length > 0);
+    }
 }
@@ -0,0 +1,400 @@
+use std::ops::Range;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::{Coord, Facing, Guard, Point};
+
+/// What a guard does instead of stepping into a blocked cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnPolicy {
+    Left,
+    Right,
+    Reverse,
+}
+
+impl TurnPolicy {
+    fn turn(self, facing: Facing) -> Facing {
+        match (self, facing) {
+            (TurnPolicy::Right, Facing::North) | (TurnPolicy::Left, Facing::South) => Facing::East,
+            (TurnPolicy::Right, Facing::East) | (TurnPolicy::Left, Facing::West) => Facing::South,
+            (TurnPolicy::Right, Facing::South) | (TurnPolicy::Left, Facing::North) => Facing::West,
+            (TurnPolicy::Right, Facing::West) | (TurnPolicy::Left, Facing::East) => Facing::North,
+            (TurnPolicy::Reverse, Facing::North) => Facing::South,
+            (TurnPolicy::Reverse, Facing::South) => Facing::North,
+            (TurnPolicy::Reverse, Facing::West) => Facing::East,
+            (TurnPolicy::Reverse, Facing::East) => Facing::West,
+        }
+    }
+}
+
+/// Obstacles only block guards trying to enter their cell, they never push guards around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Obstacle {
+    Fixed(Point),
+    /// Occupies `path[t % path.len()]` at tick `t`.
+    Moving(Vec<Point>),
+    /// Occupies `at` whenever `t % period` lies in `present`.
+    Blinking {
+        at: Point,
+        period: u32,
+        present: Range<u32>,
+    },
+}
+
+impl Obstacle {
+    fn period(&self) -> u32 {
+        match self {
+            Obstacle::Fixed(_) => 1,
+            Obstacle::Moving(path) => path.len() as u32,
+            Obstacle::Blinking { period, .. } => *period,
+        }
+    }
+
+    fn position(&self, tick: u32) -> Option<Point> {
+        match self {
+            Obstacle::Fixed(at) => Some(*at),
+            Obstacle::Moving(path) => Some(path[tick as usize % path.len()]),
+            Obstacle::Blinking {
+                at,
+                period,
+                present,
+            } => present.contains(&(tick % period)).then_some(*at),
+        }
+    }
+}
+
+/// Several guards trying to enter `point` in the same tick. None of them moves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub tick: u32,
+    pub point: Point,
+    pub guards: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatrolOutcome {
+    /// The last guard left the map during tick `tick - 1`. With no guard on the map to begin
+    /// with, `tick` is 0.
+    AllLeft { tick: u32 },
+    /// The state at tick `start + period` equals the state at tick `start`.
+    Periodic { start: u32, period: u32 },
+    /// Neither happened within the tick limit.
+    Undecided,
+}
+
+#[derive(Debug, Clone)]
+pub struct PatrolReport {
+    /// Cells any guard has stood on.
+    pub covered: FxHashSet<Point>,
+    /// Number of distinct cells each guard has stood on.
+    pub covered_by: Vec<usize>,
+    pub collisions: Vec<Collision>,
+    /// The tick in which each guard stepped off the map.
+    pub exits: Vec<Option<u32>>,
+    pub outcome: PatrolOutcome,
+}
+
+/// Patrol simulator for any number of guards. Each tick every guard on the map either steps
+/// forward or, if the cell ahead is blocked by an obstacle or another guard, turns.
+#[derive(Debug, Clone)]
+pub struct Patrol {
+    width: Coord,
+    height: Coord,
+    guards: Vec<(Guard, TurnPolicy)>,
+    fixed: FxHashSet<Point>,
+    timed: Vec<Obstacle>,
+}
+
+impl Patrol {
+    /// `width` and `height` follow the 1-based convention of [`Point`], so the map spans
+    /// `1..width` by `1..height`.
+    pub fn new(width: Coord, height: Coord) -> Self {
+        Self {
+            width,
+            height,
+            guards: Vec::new(),
+            fixed: FxHashSet::default(),
+            timed: Vec::new(),
+        }
+    }
+
+    /// Reads a map in puzzle notation. Every `^>v<` is a guard using `policy`.
+    pub fn parse(puzzle: &str, policy: TurnPolicy) -> Self {
+        let lines: Vec<&str> = puzzle.lines().filter(|line| !line.is_empty()).collect();
+        let width = lines.first().map_or(0, |line| line.len()) as Coord + 1;
+        let mut patrol = Self::new(width, lines.len() as Coord + 1);
+        for (y, line) in lines.iter().enumerate() {
+            for (x, ch) in line.bytes().enumerate() {
+                let point = Point(x as Coord + 1, y as Coord + 1);
+                let facing = match ch {
+                    b'#' => {
+                        patrol.fixed.insert(point);
+                        continue;
+                    }
+                    b'^' => Facing::North,
+                    b'>' => Facing::East,
+                    b'v' => Facing::South,
+                    b'<' => Facing::West,
+                    _ => continue,
+                };
+                patrol.guards.push((Guard(point, facing), policy));
+            }
+        }
+        patrol
+    }
+
+    pub fn with_guard(mut self, guard: Guard, policy: TurnPolicy) -> Self {
+        self.guards.push((guard, policy));
+        self
+    }
+
+    /// Panics on a moving obstacle without a path or a blinking one with period 0.
+    pub fn with_obstacle(mut self, obstacle: Obstacle) -> Self {
+        match &obstacle {
+            Obstacle::Moving(path) => assert!(!path.is_empty(), "moving obstacle without a path"),
+            Obstacle::Blinking { period, .. } => {
+                assert!(*period > 0, "blinking obstacle with period 0")
+            }
+            Obstacle::Fixed(_) => {}
+        }
+        match obstacle {
+            Obstacle::Fixed(at) => {
+                self.fixed.insert(at);
+            }
+            obstacle => self.timed.push(obstacle),
+        }
+        self
+    }
+
+    pub fn guards(&self) -> impl Iterator<Item = &(Guard, TurnPolicy)> {
+        self.guards.iter()
+    }
+
+    /// Ticks after which all obstacles are back in their starting positions, or `None` if that
+    /// does not fit a `u32`.
+    fn obstacle_period(&self) -> Option<u32> {
+        fn gcd(a: u32, b: u32) -> u32 {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+        self.timed
+            .iter()
+            .map(Obstacle::period)
+            .try_fold(1u32, |lcm, period| {
+                (lcm / gcd(lcm, period)).checked_mul(period)
+            })
+    }
+
+    fn blocked_at(&self, tick: u32) -> FxHashSet<Point> {
+        self.timed
+            .iter()
+            .filter_map(|obstacle| obstacle.position(tick))
+            .collect()
+    }
+
+    /// Simulates up to `max_ticks` ticks, stopping early once all guards left or the whole
+    /// state repeats.
+    ///
+    /// Detecting repeats keeps the guards of every tick simulated, and a repeat takes at least
+    /// as many ticks as the obstacles need to return to their starting positions. Pick
+    /// `max_ticks` with that memory in mind when obstacles have long periods. If their period
+    /// does not fit a `u32`, no state can repeat within the ticks simulated, so nothing is kept.
+    pub fn run(&self, max_ticks: u32) -> PatrolReport {
+        let period = self.obstacle_period();
+        let mut guards: Vec<Option<Guard>> = self
+            .guards
+            .iter()
+            .map(|&(guard, _)| Some(guard).filter(|guard| self.in_range(guard.0)))
+            .collect();
+        let mut visited: Vec<FxHashSet<Point>> = guards
+            .iter()
+            .map(|guard| guard.iter().map(|guard| guard.0).collect())
+            .collect();
+        let mut exits = vec![None; guards.len()];
+        let mut collisions = Vec::new();
+        let mut seen = FxHashMap::default();
+
+        let mut tick = 0;
+        let outcome = loop {
+            if guards.iter().all(Option::is_none) {
+                break PatrolOutcome::AllLeft { tick };
+            }
+            if let Some(period) = period {
+                if let Some(start) = seen.insert((guards.clone(), tick % period), tick) {
+                    break PatrolOutcome::Periodic {
+                        start,
+                        period: tick - start,
+                    };
+                }
+            }
+            if tick == max_ticks {
+                break PatrolOutcome::Undecided;
+            }
+
+            let blocked = self.blocked_at(tick + 1);
+            let occupied: FxHashSet<Point> = guards.iter().flatten().map(|g| g.0).collect();
+            let mut targets: FxHashMap<Point, Vec<usize>> = FxHashMap::default();
+            for (i, guard) in guards.iter().enumerate() {
+                let Some(guard) = guard else { continue };
+                let ahead = guard.1.step(guard.0);
+                if !self.fixed.contains(&ahead)
+                    && !blocked.contains(&ahead)
+                    && !occupied.contains(&ahead)
+                {
+                    targets.entry(ahead).or_default().push(i);
+                }
+            }
+            let mut moving = vec![false; guards.len()];
+            for (point, contenders) in targets {
+                if let [i] = contenders[..] {
+                    moving[i] = true;
+                } else {
+                    collisions.push(Collision {
+                        tick,
+                        point,
+                        guards: contenders,
+                    });
+                }
+            }
+
+            for (i, slot) in guards.iter_mut().enumerate() {
+                let Some(guard) = slot else { continue };
+                if !moving[i] {
+                    guard.1 = self.guards[i].1.turn(guard.1);
+                    continue;
+                }
+                guard.0 = guard.1.step(guard.0);
+                if self.in_range(guard.0) {
+                    visited[i].insert(guard.0);
+                } else {
+                    *slot = None;
+                    exits[i] = Some(tick);
+                }
+            }
+            tick += 1;
+        };
+
+        collisions.sort_unstable_by_key(|collision| (collision.tick, collision.guards[0]));
+        PatrolReport {
+            covered_by: visited.iter().map(FxHashSet::len).collect(),
+            covered: visited.into_iter().flatten().collect(),
+            collisions,
+            exits,
+            outcome,
+        }
+    }
+
+    fn in_range(&self, point: Point) -> bool {
+        point.in_range(self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_guard_matches_part1() {
+        let report = Patrol::parse(include_str!("test.txt"), TurnPolicy::Right).run(u32::MAX);
+        assert_eq!(report.covered.len(), 41);
+        assert!(matches!(report.outcome, PatrolOutcome::AllLeft { .. }));
+
+        let looping = Patrol::parse(include_str!("test.txt"), TurnPolicy::Right)
+            .with_obstacle(Obstacle::Fixed(Point(4, 7)));
+        let report = looping.run(u32::MAX);
+        assert!(matches!(report.outcome, PatrolOutcome::Periodic { .. }));
+        assert_eq!(report.exits, vec![None]);
+    }
+
+    #[test]
+    fn head_on_collision() {
+        let report = Patrol::parse(".....\n>...<\n.....\n", TurnPolicy::Right).run(100);
+        assert_eq!(
+            report.collisions,
+            vec![Collision {
+                tick: 1,
+                point: Point(3, 2),
+                guards: vec![0, 1],
+            }]
+        );
+        assert_eq!(report.covered.len(), 6);
+        assert_eq!(report.exits, vec![Some(3), Some(3)]);
+        assert_eq!(report.outcome, PatrolOutcome::AllLeft { tick: 4 });
+    }
+
+    #[test]
+    fn reverse_and_blinking() {
+        let patrol = Patrol::parse("#>..#\n", TurnPolicy::Reverse);
+        let report = patrol.run(100);
+        assert_eq!(
+            report.outcome,
+            PatrolOutcome::Periodic {
+                start: 0,
+                period: 6
+            }
+        );
+        assert_eq!(report.covered_by, vec![3]);
+
+        // The middle cell is blocked on even ticks, so once past it the guard is trapped.
+        let report = patrol
+            .with_obstacle(Obstacle::Blinking {
+                at: Point(3, 1),
+                period: 2,
+                present: 0..1,
+            })
+            .run(100);
+        assert_eq!(report.covered_by, vec![3]);
+        assert_eq!(
+            report.outcome,
+            PatrolOutcome::Periodic {
+                start: 2,
+                period: 2
+            }
+        );
+    }
+
+    #[test]
+    fn no_guards() {
+        let report = Patrol::new(5, 5).run(10);
+        assert_eq!(report.outcome, PatrolOutcome::AllLeft { tick: 0 });
+        let outside =
+            Patrol::new(5, 5).with_guard(Guard(Point(9, 9), Facing::North), TurnPolicy::Right);
+        assert_eq!(outside.run(10).outcome, PatrolOutcome::AllLeft { tick: 0 });
+    }
+
+    #[test]
+    #[should_panic(expected = "moving obstacle without a path")]
+    fn empty_path() {
+        Patrol::new(5, 5).with_obstacle(Obstacle::Moving(vec![]));
+    }
+
+    #[test]
+    #[should_panic(expected = "blinking obstacle with period 0")]
+    fn zero_period() {
+        Patrol::new(5, 5).with_obstacle(Obstacle::Blinking {
+            at: Point(2, 2),
+            period: 0,
+            present: 0..0,
+        });
+    }
+
+    #[test]
+    fn overflowing_obstacle_period() {
+        let looping = Patrol::parse(include_str!("test.txt"), TurnPolicy::Right)
+            .with_obstacle(Obstacle::Fixed(Point(4, 7)));
+        let far = [65521, 65519, 65537]
+            .into_iter()
+            .fold(looping, |patrol, period| {
+                patrol.with_obstacle(Obstacle::Blinking {
+                    at: Point(50, 50),
+                    period,
+                    present: 0..1,
+                })
+            });
+        assert_eq!(far.obstacle_period(), None);
+        assert_eq!(far.run(1000).outcome, PatrolOutcome::Undecided);
+    }
+}
@@ -155,6 +155,372 @@ pub fn first_line_length(input: &[u8]) -> usize {
         .unwrap_or(input.len())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub fn turn_right(self) -> Self {
+        Self::ALL[(self as usize + 1) % 4]
+    }
+
+    pub fn turn_left(self) -> Self {
+        Self::ALL[(self as usize + 3) % 4]
+    }
+
+    pub fn reverse(self) -> Self {
+        Self::ALL[(self as usize + 2) % 4]
+    }
+}
+
+const NO_STOP: u32 = u32::MAX;
+
+/// Obstacle grid which knows for every cell and direction where a ray starting there stops,
+/// i.e. the last free cell before the next obstacle, so casting is a single lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RayGrid {
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    stops: [Vec<u32>; 4],
+}
+
+impl RayGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            blocked: vec![false; width * height],
+            stops: std::array::from_fn(|_| vec![NO_STOP; width * height]),
+        }
+    }
+
+    pub fn from_obstacles(
+        width: usize,
+        height: usize,
+        obstacles: impl IntoIterator<Item = (usize, usize)>,
+    ) -> Self {
+        let mut grid = Self::new(width, height);
+        for (x, y) in obstacles {
+            grid.insert(x, y);
+        }
+        grid
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_blocked(&self, x: usize, y: usize) -> bool {
+        self.blocked[y * self.width + x]
+    }
+
+    /// Where a ray from the free cell `(x, y)` stops, or `None` if it leaves the grid.
+    #[inline(always)]
+    pub fn cast(&self, x: usize, y: usize, direction: Direction) -> Option<(usize, usize)> {
+        match self.stops[direction as usize][y * self.width + x] {
+            NO_STOP => None,
+            stop => Some((stop as usize % self.width, stop as usize / self.width)),
+        }
+    }
+
+    fn neighbour(&self, cell: usize, direction: Direction) -> Option<usize> {
+        let (x, y) = (cell % self.width, cell / self.width);
+        match direction {
+            Direction::North if y > 0 => Some(cell - self.width),
+            Direction::South if y + 1 < self.height => Some(cell + self.width),
+            Direction::West if x > 0 => Some(cell - 1),
+            Direction::East if x + 1 < self.width => Some(cell + 1),
+            _ => None,
+        }
+    }
+
+    /// Recomputes the stops towards `direction` for the free run of cells ending at `end`,
+    /// which is the cell furthest along `direction`.
+    fn update_segment(&mut self, direction: Direction, end: usize) {
+        let stop = match self.neighbour(end, direction) {
+            None => NO_STOP,
+            Some(next) if self.blocked[next] => end as u32,
+            Some(next) => self.stops[direction as usize][next],
+        };
+        let mut cell = Some(end);
+        while let Some(c) = cell.filter(|&c| !self.blocked[c]) {
+            self.stops[direction as usize][c] = stop;
+            cell = self.neighbour(c, direction.reverse());
+        }
+    }
+
+    /// Adds an obstacle, updating only the cells in its row and column up to the next obstacles.
+    pub fn insert(&mut self, x: usize, y: usize) {
+        let cell = y * self.width + x;
+        if self.blocked[cell] {
+            return;
+        }
+        self.blocked[cell] = true;
+        for direction in Direction::ALL {
+            if let Some(before) = self.neighbour(cell, direction.reverse()) {
+                self.update_segment(direction, before);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, x: usize, y: usize) {
+        let cell = y * self.width + x;
+        if !self.blocked[cell] {
+            return;
+        }
+        self.blocked[cell] = false;
+        for direction in Direction::ALL {
+            self.update_segment(direction, cell);
+        }
+    }
+}
+
+/// Brent's cycle detection on the sequence `start, step(start), ...`, which ends once `step`
+/// returns `None`. Returns the index where the cycle starts and its length.
+pub fn find_cycle<T: Copy + Eq>(start: T, step: impl Fn(T) -> Option<T>) -> Option<(usize, usize)> {
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = start;
+    let mut hare = step(start)?;
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare;
+            power *= 2;
+            length = 0;
+        }
+        hare = step(hare)?;
+        length += 1;
+    }
+
+    let mut tortoise = start;
+    let mut hare = start;
+    for _ in 0..length {
+        hare = step(hare)?;
+    }
+    let mut offset = 0;
+    while tortoise != hare {
+        tortoise = step(tortoise)?;
+        hare = step(hare)?;
+        offset += 1;
+    }
+    Some((offset, length))
+}
+
+pub trait Grid<T> {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn get(&self, x: usize, y: usize) -> T;
+}
+
+impl<T: Copy> Grid<T> for Vec<Vec<T>> {
+    fn width(&self) -> usize {
+        self.first().map_or(0, Vec::len)
+    }
+
+    fn height(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&self, x: usize, y: usize) -> T {
+        self[y][x]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    Four,
+    /// Cells touching at a corner are connected as well.
+    Eight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentStats {
+    pub area: usize,
+    /// Edges between the component and other cells or the outside.
+    pub perimeter: Option<usize>,
+    /// Corners of the component's outline, which equals its number of straight sides.
+    pub corners: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Components {
+    pub width: usize,
+    /// Row-major component index of every cell, numbered by first appearance.
+    pub labels: Vec<usize>,
+    pub stats: Vec<ComponentStats>,
+}
+
+impl Components {
+    pub fn label(&self, x: usize, y: usize) -> usize {
+        self.labels[y * self.width + x]
+    }
+
+    pub fn height(&self) -> usize {
+        self.labels.len().checked_div(self.width).unwrap_or(0)
+    }
+
+    fn same(&self, label: usize, x: isize, y: isize) -> bool {
+        (0..self.width as isize).contains(&x)
+            && (0..self.height() as isize).contains(&y)
+            && self.label(x as usize, y as usize) == label
+    }
+}
+
+/// Two-pass connected-component labelling with union-find over the already visited neighbours.
+#[derive(Debug, Clone, Copy)]
+pub struct ComponentLabeller {
+    connectivity: Connectivity,
+    perimeter: bool,
+    corners: bool,
+}
+
+impl ComponentLabeller {
+    pub fn new(connectivity: Connectivity) -> Self {
+        Self {
+            connectivity,
+            perimeter: false,
+            corners: false,
+        }
+    }
+
+    pub fn with_perimeter(mut self) -> Self {
+        self.perimeter = true;
+        self
+    }
+
+    pub fn with_corners(mut self) -> Self {
+        self.corners = true;
+        self
+    }
+
+    /// Labels `grid`, treating neighbouring cells `a` and `b` as connected if `same(a, b)`.
+    /// `same` has to be an equivalence relation.
+    pub fn label<T>(&self, grid: &impl Grid<T>, same: impl Fn(&T, &T) -> bool) -> Components {
+        let (width, height) = (grid.width(), grid.height());
+        let mut parents: Vec<usize> = Vec::new();
+        let find = |parents: &mut Vec<usize>, mut id: usize| {
+            while parents[id] != id {
+                parents[id] = parents[parents[id]];
+                id = parents[id];
+            }
+            id
+        };
+
+        let mut labels = Vec::with_capacity(width * height);
+        let mut previous_row: Vec<T> = Vec::with_capacity(width);
+        let mut current_row: Vec<T> = Vec::with_capacity(width);
+        for y in 0..height {
+            for x in 0..width {
+                let cell = grid.get(x, y);
+                let mut label = None;
+                let mut neighbours = [None; 4];
+                if x > 0 {
+                    neighbours[0] = Some((&current_row[x - 1], labels[labels.len() - 1]));
+                }
+                if y > 0 {
+                    let north = labels.len() - width;
+                    neighbours[1] = Some((&previous_row[x], labels[north]));
+                    if self.connectivity == Connectivity::Eight {
+                        if x > 0 {
+                            neighbours[2] = Some((&previous_row[x - 1], labels[north - 1]));
+                        }
+                        if x + 1 < width {
+                            neighbours[3] = Some((&previous_row[x + 1], labels[north + 1]));
+                        }
+                    }
+                }
+                for (neighbour, neighbour_label) in neighbours.into_iter().flatten() {
+                    if !same(&cell, neighbour) {
+                        continue;
+                    }
+                    let root = find(&mut parents, neighbour_label);
+                    match label {
+                        None => label = Some(root),
+                        Some(own) if own != root => {
+                            let (low, high) = (own.min(root), own.max(root));
+                            parents[high] = low;
+                            label = Some(low);
+                        }
+                        Some(_) => {}
+                    }
+                }
+                labels.push(label.unwrap_or_else(|| {
+                    parents.push(parents.len());
+                    parents.len() - 1
+                }));
+                current_row.push(cell);
+            }
+            std::mem::swap(&mut previous_row, &mut current_row);
+            current_row.clear();
+        }
+
+        let mut compact = vec![usize::MAX; parents.len()];
+        let mut stats = Vec::new();
+        for label in labels.iter_mut() {
+            let root = find(&mut parents, *label);
+            if compact[root] == usize::MAX {
+                compact[root] = stats.len();
+                stats.push(ComponentStats {
+                    area: 0,
+                    perimeter: self.perimeter.then_some(0),
+                    corners: self.corners.then_some(0),
+                });
+            }
+            *label = compact[root];
+            stats[*label].area += 1;
+        }
+
+        let mut components = Components {
+            width,
+            labels,
+            stats,
+        };
+        if self.perimeter || self.corners {
+            for y in 0..height as isize {
+                for x in 0..width as isize {
+                    let label = components.label(x as usize, y as usize);
+                    let same = |dx: isize, dy: isize| components.same(label, x + dx, y + dy);
+                    let perimeter = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                        .into_iter()
+                        .filter(|&(dx, dy)| !same(dx, dy))
+                        .count();
+                    let corners = [(-1, -1), (1, -1), (1, 1), (-1, 1)]
+                        .into_iter()
+                        .filter(|&(dx, dy)| {
+                            let (horizontal, vertical) = (same(dx, 0), same(0, dy));
+                            horizontal == vertical && !(horizontal && same(dx, dy))
+                        })
+                        .count();
+                    let stats = &mut components.stats[label];
+                    if let Some(total) = &mut stats.perimeter {
+                        *total += perimeter;
+                    }
+                    if let Some(total) = &mut stats.corners {
+                        *total += corners;
+                    }
+                }
+            }
+        }
+        components
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -172,4 +538,69 @@ mod tests {
         assert_eq!(view.len(), 5);
         assert_eq!(view, orig);
     }
+
+    #[test]
+    fn ray_grid_insert_remove() {
+        let mut grid = RayGrid::from_obstacles(5, 4, [(2, 0), (0, 2)]);
+        assert_eq!(grid.cast(0, 0, Direction::East), Some((1, 0)));
+        assert_eq!(grid.cast(4, 0, Direction::West), Some((3, 0)));
+        assert_eq!(grid.cast(0, 0, Direction::South), Some((0, 1)));
+        assert_eq!(grid.cast(0, 3, Direction::North), Some((0, 3)));
+        assert_eq!(grid.cast(1, 1, Direction::East), None);
+
+        grid.insert(4, 1);
+        assert_eq!(grid.cast(1, 1, Direction::East), Some((3, 1)));
+        assert_eq!(grid.cast(4, 3, Direction::North), Some((4, 2)));
+        grid.remove(2, 0);
+        assert_eq!(grid.cast(0, 0, Direction::East), None);
+        assert_eq!(grid.cast(2, 0, Direction::West), None);
+        assert_eq!(grid.cast(2, 3, Direction::North), None);
+
+        let rebuilt = RayGrid::from_obstacles(5, 4, [(0, 2), (4, 1)]);
+        assert_eq!(grid, rebuilt);
+    }
+
+    #[test]
+    fn ray_grid_sizes() {
+        for (width, height) in [(0, 0), (0, 3), (3, 0)] {
+            let grid = RayGrid::new(width, height);
+            assert_eq!((grid.width(), grid.height()), (width, height));
+        }
+        let single = RayGrid::new(1, 1);
+        assert_eq!(single.cast(0, 0, Direction::North), None);
+        let column = RayGrid::from_obstacles(1, 3, [(0, 2)]);
+        assert_eq!(column.cast(0, 0, Direction::South), Some((0, 1)));
+    }
+
+    #[test]
+    fn brent_cycle() {
+        assert_eq!(find_cycle(0, |x| Some((x + 1) % 5)), Some((0, 5)));
+        assert_eq!(
+            find_cycle(0, |x| Some(if x < 7 { x + 1 } else { 3 })),
+            Some((3, 5))
+        );
+        assert_eq!(find_cycle(0, |x| (x < 10).then_some(x + 1)), None);
+    }
+
+    #[test]
+    fn components() {
+        let grid: Vec<Vec<u8>> = ["AAB", "ABA", "BAA"]
+            .iter()
+            .map(|row| row.bytes().collect())
+            .collect();
+        let four = ComponentLabeller::new(Connectivity::Four)
+            .with_perimeter()
+            .with_corners()
+            .label(&grid, |a, b| a == b);
+        assert_eq!(four.labels, vec![0, 0, 1, 0, 2, 3, 4, 3, 3]);
+        assert_eq!(four.stats[0].area, 3);
+        assert_eq!(four.stats[0].perimeter, Some(8));
+        assert_eq!(four.stats[0].corners, Some(6));
+        assert_eq!(four.stats[2].corners, Some(4));
+
+        let eight = ComponentLabeller::new(Connectivity::Eight).label(&grid, |a, b| a == b);
+        assert_eq!(eight.labels, vec![0, 0, 1, 0, 1, 0, 1, 0, 0]);
+        assert_eq!(eight.stats[1].area, 3);
+        assert_eq!(eight.stats[1].perimeter, None);
+    }
 }